@@ -0,0 +1,55 @@
+use crate::psf2_writer::Psf2Font;
+
+/// The number of array bytes on each line of a C header.
+const C_HEADER_BYTES_PER_LINE: usize = 12;
+
+/// Turns `name` into a valid C identifier: characters other than ASCII letters, digits, and
+/// underscores become underscores, and an underscore is prepended if the result would be empty or
+/// start with a digit.
+pub fn c_identifier(name: &str) -> String {
+    let mut identifier: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() {c} else {'_'})
+        .collect();
+    if identifier.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    return identifier;
+}
+
+/// Writes `font` as a C header for embedding in firmware: `#define`s for the glyph dimensions,
+/// glyph count, and file length, prefixed with `name` in upper case, and the bytes of the whole
+/// PSF2 file (including its Unicode table, if any) as `const uint8_t name[]`. `name` must be a
+/// valid C identifier. Any metadata of the font's Unicode table is written in a comment at the
+/// top.
+pub fn c_header(font: &Psf2Font, name: &str) -> String {
+    let bytes = font.write();
+    let prefix = name.to_ascii_uppercase();
+    let mut header = String::new();
+    let meta_fields = font.unicode_table.as_ref().map(|t| t.meta.fields()).unwrap_or_default();
+    if !meta_fields.is_empty() {
+        header.push_str("/*\n");
+        for (label, value) in meta_fields {
+            // a `*/` in the value would end the comment early.
+            header.push_str(&format!(" * Charset {}: {}\n", label.to_ascii_lowercase(), value.replace("*/", "* /")));
+        }
+        header.push_str(" */\n\n");
+    }
+    header.push_str(&format!("#ifndef {prefix}_H\n#define {prefix}_H\n\n#include <stdint.h>\n\n"));
+    for (field, value) in [
+        ("WIDTH", font.header.glyph_width),
+        ("HEIGHT", font.header.glyph_height),
+        ("GLYPH_COUNT", font.header.glyph_count),
+        ("GLYPH_SIZE", font.header.glyph_size),
+        ("HAS_UNICODE_TABLE", font.header.unicode_table_exists as u32),
+        ("LENGTH", bytes.len() as u32),
+    ] {
+        header.push_str(&format!("#define {}_{} {}\n", prefix, field, value));
+    }
+    header.push_str(&format!("\nstatic const uint8_t {}[{}_LENGTH] = {{\n", name, prefix));
+    for line in bytes.chunks(C_HEADER_BYTES_PER_LINE) {
+        let hex: Vec<String> = line.iter().map(|b| format!("0x{:02x},", b)).collect();
+        header.push_str(&format!("    {}\n", hex.join(" ")));
+    }
+    header.push_str(&format!("}};\n\n#endif /* {}_H */\n", prefix));
+    return header;
+}
@@ -0,0 +1,5 @@
+/// Returns a human-readable description of a character, for use in logs and error messages.
+/// For example, `'A'` is described as `'A' (U+0041, LATIN CAPITAL LETTER A)`.
+pub fn char_name(c: char) -> String {
+    return format!("{:?} (U+{:04X}, {})", c, u32::from(c), charname::get_name(u32::from(c)));
+}
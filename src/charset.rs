@@ -0,0 +1,59 @@
+use clap::ValueEnum;
+
+use crate::unicode_table::UnicodeTable;
+
+/// The preset used when neither a preset nor a Unicode table is given.
+pub const DEFAULT_CHARSET_PRESET: CharsetPreset = CharsetPreset::Latin1;
+
+/// The characters of code page 437, the IBM PC character set built into VGA text modes, in order
+/// from 0x80 up.
+const CP437_UPPER_HALF: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+    ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+    αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+/// The graphics code page 437 draws for control characters 0x01 through 0x1f.
+const CP437_CONTROL_GRAPHICS: &str = "☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+/// The graphic code page 437 draws for 0x7f.
+const CP437_HOUSE: char = '⌂';
+
+/// A ready-made set of glyphs to convert, for when no Unicode table is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CharsetPreset {
+    /// The 128 ASCII characters, U+0000 through U+007F.
+    Ascii,
+    /// The 256 characters of ISO 8859-1, U+0000 through U+00FF.
+    Latin1,
+    /// The 256 characters of code page 437, at their VGA glyph indices, with a Unicode table.
+    Vga,
+    /// The first 512 codepoints, U+0000 through U+01FF: Latin-1 and the Latin Extended-A and
+    /// part of Latin Extended-B blocks. The most glyphs the Linux console accepts.
+    Full,
+}
+
+impl CharsetPreset {
+    /// The number of glyphs in the preset.
+    pub const fn glyph_count(self) -> u32 {
+        return match self {
+            CharsetPreset::Ascii => 128,
+            CharsetPreset::Latin1 | CharsetPreset::Vga => 256,
+            CharsetPreset::Full => 512,
+        };
+    }
+
+    /// The Unicode table for the preset, if its glyph indices are not simply codepoints.
+    pub fn unicode_table(self) -> Option<UnicodeTable> {
+        return match self {
+            CharsetPreset::Vga => Some(UnicodeTable::from_chars(cp437())),
+            _ => None,
+        };
+    }
+}
+
+/// The characters of code page 437, indexed by their byte values. 0x00 stays U+0000.
+pub fn cp437() -> Vec<char> {
+    return std::iter::once('\0')
+        .chain(CP437_CONTROL_GRAPHICS.chars())
+        .chain((0x20..0x7f).map(char::from))
+        .chain(std::iter::once(CP437_HOUSE))
+        .chain(CP437_UPPER_HALF.chars())
+        .collect();
+}
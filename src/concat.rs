@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+
+use crate::psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header};
+use crate::unicode_table::{UnicodeTable, UnicodeTableMeta};
+
+/// What to do when more than one glyph claims the same grapheme in a concatenated font.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CollisionPolicy {
+    /// Keep the grapheme on the earliest glyph that claims it.
+    First,
+    /// Keep the grapheme on the latest glyph that claims it.
+    Last,
+    /// Exit with an error.
+    Error,
+}
+
+/// Concatenates the glyph sets of `fonts`, in order, into one font with a merged Unicode table.
+/// Fonts without a Unicode table map glyph `i` to `U+i`. If `pad` is set, glyphs are padded to
+/// the largest cell; otherwise all fonts must share the same dimensions.
+pub fn concat_fonts(fonts: Vec<Psf2Font>, pad: bool, on_collision: CollisionPolicy) 
+    -> Result<Psf2Font, Box<dyn std::error::Error>> {
+    let mut glyphs = vec![];
+    let mut data: Vec<Vec<String>> = vec![];
+    for font in fonts.into_iter() {
+        let glyph_count = font.glyphs.glyphs().len() as u32;
+        data.extend(match font.unicode_table {
+            Some(uc) => uc.data,
+            None => UnicodeTable::identity(glyph_count).data,
+        });
+        glyphs.extend(font.glyphs.into_glyphs());
+    }
+
+    // the glyph index that currently claims each grapheme.
+    let mut owner: HashMap<String, usize> = HashMap::new();
+    for i in 0..data.len() {
+        let mut kept: Vec<String> = vec![];
+        for grapheme in std::mem::take(&mut data[i]).into_iter() {
+            match owner.get(&grapheme).copied() {
+                None => {
+                    owner.insert(grapheme.clone(), i);
+                    kept.push(grapheme);
+                }
+                Some(j) if j == i => {}
+                Some(j) => match on_collision {
+                    CollisionPolicy::First => {}
+                    CollisionPolicy::Last => {
+                        data[j].retain(|g| *g != grapheme);
+                        owner.insert(grapheme.clone(), i);
+                        kept.push(grapheme);
+                    }
+                    CollisionPolicy::Error => return Err(format!("Glyphs {} and {} of the concatenated font \
+                        both represent {:?}. Use --on-collision first or last to pick one.", j, i, grapheme).into()),
+                }
+            }
+        }
+        data[i] = kept;
+    }
+
+    let glyphs = Psf2GlyphSet::from_glyphs(glyphs, pad)?;
+    let header = Psf2Header{
+        unicode_table_exists: true,
+        glyph_count: data.len() as u32,
+        glyph_size: glyphs.length,
+        glyph_height: glyphs.height,
+        glyph_width: glyphs.width,
+    };
+    return Ok(Psf2Font{header, glyphs, unicode_table: Some(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0})});
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glyph::Glyph;
+
+    /// A font of one-row glyphs, each drawn by `art` and mapped to `graphemes`.
+    fn font(glyphs: &[(&str, &[&str])]) -> Psf2Font {
+        let glyph_set = Psf2GlyphSet::from_glyphs(glyphs.iter()
+            .map(|(art, _)| Glyph::from_ascii_art(&[art], String::new(), 1).unwrap())
+            .collect(), false).unwrap();
+        let data = glyphs.iter().map(|(_, graphemes)| graphemes.iter().map(|g| g.to_string()).collect()).collect();
+        let header = Psf2Header{
+            unicode_table_exists: true,
+            glyph_count: glyphs.len() as u32,
+            glyph_size: glyph_set.length,
+            glyph_height: glyph_set.height,
+            glyph_width: glyph_set.width,
+        };
+        return Psf2Font{header, glyphs: glyph_set, unicode_table: Some(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0})};
+    }
+
+    /// Two fonts that both map 'A', to different glyphs.
+    fn colliding_fonts() -> Vec<Psf2Font> {
+        return vec![
+            font(&[("#..", &["A"]), ("...", &["B"])]),
+            font(&[(".#.", &["A", "C"])]),
+        ];
+    }
+
+    fn table(font: &Psf2Font) -> Vec<Vec<String>> {
+        return font.unicode_table.as_ref().unwrap().data.clone();
+    }
+
+    #[test]
+    fn first_policy_keeps_the_earliest_glyph() {
+        let font = concat_fonts(colliding_fonts(), false, CollisionPolicy::First).unwrap();
+        assert_eq!(font.header.glyph_count, 3);
+        assert_eq!(table(&font), [vec!["A"], vec!["B"], vec!["C"]]);
+    }
+
+    #[test]
+    fn last_policy_keeps_the_latest_glyph() {
+        let font = concat_fonts(colliding_fonts(), false, CollisionPolicy::Last).unwrap();
+        assert_eq!(font.header.glyph_count, 3);
+        assert_eq!(table(&font), [vec![], vec!["B"], vec!["A", "C"]]);
+        // the glyph that lost its grapheme keeps its place and its bitmap.
+        assert_eq!(font.glyphs.glyphs()[0].to_ascii_art(), "#..");
+    }
+
+    #[test]
+    fn error_policy_rejects_a_collision() {
+        let error = concat_fonts(colliding_fonts(), false, CollisionPolicy::Error).err().unwrap();
+        assert!(error.to_string().contains("Glyphs 0 and 2"), "{}", error);
+    }
+
+    #[test]
+    fn fonts_of_different_sizes_need_pad() {
+        let fonts = || vec![font(&[("#..", &["A"])]), font(&[("#...", &["B"])])];
+        assert!(concat_fonts(fonts(), false, CollisionPolicy::Error).is_err());
+        let font = concat_fonts(fonts(), true, CollisionPolicy::Error).unwrap();
+        assert_eq!((font.header.glyph_height, font.header.glyph_width), (1, 4));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::charset::DEFAULT_CHARSET_PRESET;
+use crate::errors::ConvertError;
+use crate::profile::GlyphTimings;
+use crate::psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header, RenderGrapheme};
+use crate::ttf_parser::{CellWidth, TtfParser};
+use crate::unicode_table::UnicodeTable;
+
+/// Options for `convert`. The default renders the codepoints of `DEFAULT_CHARSET_PRESET` at their
+/// advance widths, without padding.
+pub struct ConvertOptions {
+    /// The Unicode table to build the font from. If `None`, glyphs are rendered for codepoints
+    /// `U+0000` through `U+(glyph_count - 1)`, and the font has no Unicode table.
+    pub unicode_table: Option<UnicodeTable>,
+    /// The number of glyphs to render when there is no Unicode table.
+    pub glyph_count: u32,
+    /// Whether to pad every glyph to the size of the largest one, rather than returning an error
+    /// if glyph sizes differ.
+    pub pad: bool,
+    /// Where the width of each rasterized glyph's canvas comes from.
+    pub cell_width: CellWidth,
+    /// Whether to render Unicode table entries the font does not define as its `.notdef` glyph,
+    /// rather than returning an error.
+    pub allow_missing: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        return Self{unicode_table: None, glyph_count: DEFAULT_CHARSET_PRESET.glyph_count(), pad: false, cell_width: CellWidth::Advance, allow_missing: false};
+    }
+}
+
+/// Converts the contents of a TTF/OTF font file to a PSF2 font at `height` pixels, returning the
+/// PSF2 file's bytes without touching the filesystem.
+pub fn convert(font_bytes: &[u8], height: u32, options: ConvertOptions) -> Result<Vec<u8>, ConvertError> {
+    let mut ttf_parser = TtfParser::from_bytes(font_bytes.to_vec(), height)?;
+    ttf_parser.set_cell_width(options.cell_width);
+    let mut timings = GlyphTimings::new(0);
+    let rendered_glyphs = match &options.unicode_table {
+        Some(uc) => Psf2GlyphSet::render_with_unicode_table(&ttf_parser, uc, RenderGrapheme::First, 
+            options.allow_missing, &mut timings)?,
+        None => Psf2GlyphSet::render(&ttf_parser, options.glyph_count, &mut timings)?,
+    };
+    let glyph_count = rendered_glyphs.len() as u32;
+    let glyphs = Psf2GlyphSet::from_glyphs(rendered_glyphs, options.pad)?;
+    let header = Psf2Header{
+        unicode_table_exists: options.unicode_table.is_some(),
+        glyph_count,
+        glyph_size: glyphs.length,
+        glyph_height: glyphs.height,
+        glyph_width: glyphs.width,
+    };
+    return Ok(Psf2Font{header, glyphs, unicode_table: options.unicode_table}.write());
+}
@@ -0,0 +1,129 @@
+use clap::ValueEnum;
+
+use crate::glyph::Glyph;
+
+/// How steeply `Effect::Italic` slants glyphs, unless `--italic-slope` says otherwise: the pixels
+/// each row is shifted right per row above the bottom of the cell.
+pub const DEFAULT_ITALIC_SLOPE: f32 = 0.2;
+
+/// The order in which enabled effects are applied, unless `--effects-order` says otherwise.
+pub const DEFAULT_EFFECTS_ORDER: [Effect; 8] = [Effect::FlipH, Effect::FlipV, Effect::Bold, Effect::Italic, 
+    Effect::Outline, Effect::Underline, Effect::Strikethrough, Effect::Invert];
+
+/// The parameters of effects that take one.
+#[derive(Debug, Clone, Copy)]
+pub struct EffectSettings {
+    /// How far `Effect::Italic` shifts each row per row above the bottom of the cell.
+    pub italic_slope: f32,
+    /// The row `Effect::Underline` draws its line at, counted from the top of each glyph. `None`
+    /// means the bottom row.
+    pub underline_row: Option<u32>,
+    /// The row `Effect::Strikethrough` draws its line at, counted from the top of each glyph.
+    /// `None` means the middle row.
+    pub strikethrough_row: Option<u32>,
+}
+
+/// One step of the effects pipeline built by `effects_pipeline`.
+pub type GlyphTransform = Box<dyn Fn(Glyph) -> Glyph>;
+
+/// A transform applied to every glyph's bitmap after it is rendered. Effects other than `Italic`
+/// work within the glyph's cell, so pixels pushed past its edges are lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Effect {
+    /// Mirror glyphs left to right.
+    FlipH,
+    /// Mirror glyphs top to bottom.
+    FlipV,
+    /// Thicken strokes by one pixel to the right.
+    Bold,
+    /// Slant glyphs to the right, pivoting on the bottom row of the cell. Widens the cell to fit.
+    Italic,
+    /// Replace the ink with a one-pixel outline around it.
+    Outline,
+    /// Draw a line across the glyph, by default along its bottom row.
+    Underline,
+    /// Draw a line across the glyph, by default along its middle row.
+    Strikethrough,
+    /// Swap set and blank pixels.
+    Invert,
+}
+
+impl Effect {
+    pub fn apply(self, glyph: Glyph, settings: EffectSettings) -> Glyph {
+        return match self {
+            Effect::FlipH => glyph.flip_horizontal(),
+            Effect::FlipV => glyph.flip_vertical(),
+            Effect::Bold => glyph.embolden(),
+            Effect::Italic => glyph.shear(settings.italic_slope),
+            Effect::Outline => glyph.outline(),
+            Effect::Underline => {
+                let row = settings.underline_row.unwrap_or(glyph.height.saturating_sub(1));
+                glyph.with_underline(row)
+            }
+            Effect::Strikethrough => {
+                let row = settings.strikethrough_row.unwrap_or(glyph.height / 2);
+                glyph.with_strikethrough(row)
+            }
+            Effect::Invert => glyph.invert(),
+        };
+    }
+}
+
+/// Builds the list of transforms to apply to each glyph, in order: first the effects listed in
+/// `order`, then any other `enabled` effects in `DEFAULT_EFFECTS_ORDER`. Returns an error if
+/// `order` lists an effect that is not enabled, or lists an effect more than once. Effects that
+/// take a parameter read it from `settings`.
+pub fn effects_pipeline(enabled: &[Effect], order: &[Effect], settings: EffectSettings) 
+    -> Result<Vec<GlyphTransform>, Box<dyn std::error::Error>> {
+    for (i, effect) in order.iter().enumerate() {
+        let name = effect.to_possible_value().expect("effects are never skipped").get_name().to_string();
+        if !enabled.contains(effect) {
+            return Err(format!("--effects-order lists {}, but --{} is not set.", name, name).into());
+        }
+        if order[..i].contains(effect) {
+            return Err(format!("--effects-order lists {} more than once.", name).into());
+        }
+    }
+
+    let remaining = DEFAULT_EFFECTS_ORDER.into_iter()
+        .filter(|effect| enabled.contains(effect) && !order.contains(effect));
+    return Ok(order.iter().copied().chain(remaining)
+        .map(|effect| Box::new(move |glyph| effect.apply(glyph, settings)) as GlyphTransform)
+        .collect());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: EffectSettings = EffectSettings{italic_slope: DEFAULT_ITALIC_SLOPE, underline_row: None, strikethrough_row: None};
+
+    fn run(pipeline: &[GlyphTransform], glyph: Glyph) -> Glyph {
+        return pipeline.iter().fold(glyph, |g, transform| transform(g));
+    }
+
+    #[test]
+    fn order_changes_output() {
+        let glyph = Glyph::from_ascii_art(&["#...", "#...", "...."], String::from("x"), 3).unwrap();
+        let enabled = [Effect::Bold, Effect::Outline];
+        let bold_first = effects_pipeline(&enabled, &[Effect::Bold, Effect::Outline], SETTINGS).unwrap();
+        let outline_first = effects_pipeline(&enabled, &[Effect::Outline, Effect::Bold], SETTINGS).unwrap();
+        assert_eq!(run(&bold_first, glyph.clone()).to_ascii_art(), "..#.\n..#.\n##..");
+        assert_eq!(run(&outline_first, glyph).to_ascii_art(), ".##.\n.##.\n##..");
+    }
+
+    #[test]
+    fn unlisted_effects_follow_default_order() {
+        let glyph = Glyph::from_ascii_art(&["#...", "#...", "...."], String::from("x"), 3).unwrap();
+        let enabled = [Effect::Bold, Effect::Outline];
+        let listed = effects_pipeline(&enabled, &[Effect::Bold, Effect::Outline], SETTINGS).unwrap();
+        let default = effects_pipeline(&enabled, &[], SETTINGS).unwrap();
+        assert_eq!(run(&default, glyph.clone()).data, run(&listed, glyph).data);
+    }
+
+    #[test]
+    fn order_must_list_enabled_effects_once() {
+        assert!(effects_pipeline(&[Effect::Bold], &[Effect::Invert], SETTINGS).is_err());
+        assert!(effects_pipeline(&[Effect::Bold], &[Effect::Bold, Effect::Bold], SETTINGS).is_err());
+    }
+}
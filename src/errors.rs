@@ -2,10 +2,15 @@ use ab_glyph::GlyphImageFormat;
 use ab_glyph::InvalidFont;
 use std::fmt::Formatter;
 use std::fmt::Display;
+use itertools::Itertools;
 
+use crate::char_info::char_name;
 use crate::unicode_table::Rule;
 use std::num::ParseIntError;
 
+/// The number of graphemes `GlyphSetError::UndefinedGraphemes` lists before summarizing the rest.
+const UNDEFINED_GRAPHEMES_LISTED: usize = 20;
+
 #[derive(Debug)]
 pub enum GlyphError {
     WrongDimensions { height: u32, width: u32, expected_height: u32, expected_width: u32 },
@@ -13,6 +18,11 @@ pub enum GlyphError {
     PadTooSmall { height: u32, width: u32, pad_height: u32, pad_width: u32 },
     GlyphImgFmtUnsupported { format: GlyphImageFormat },
     EmptyString,
+    StrikeHeightMismatch { height: u32, expected_height: u32 },
+    TooLarge { height: u32, width: u32, max_pixels: u32 },
+    NoSpaceGlyph,
+    /// The glyphs of a multi-character grapheme could not be combined.
+    Compose { grapheme: String, inner: Box<GlyphError> },
 }
 
 impl Display for GlyphError {
@@ -31,6 +41,16 @@ impl Display for GlyphError {
                 write!(f, "Unsupported TTF/OTF embedded bitmap format: {:?}.", format),
             GlyphError::EmptyString => 
                 write!(f, "Attempted to render empty string as a glyph."),
+            GlyphError::StrikeHeightMismatch{height, expected_height} => 
+                write!(f, "Embedded bitmap strike is {} px tall, but the requested height is {} px; \
+                use --strike-height scale or pad to adjust it.", height, expected_height),
+            GlyphError::TooLarge{height, width, max_pixels} => 
+                write!(f, "Glyph canvas of {} x {} px is too large: at most {} pixels are allowed.", 
+                height, width, max_pixels),
+            GlyphError::NoSpaceGlyph => 
+                write!(f, "The font has no glyph for U+0020 SPACE to take the cell width from."),
+            GlyphError::Compose{grapheme, inner} => 
+                write!(f, "While combining the characters of {:?}: {}", grapheme, inner),
         }
     }
 }
@@ -40,9 +60,20 @@ impl std::error::Error for GlyphError {}
 #[derive(Debug)]
 pub enum UnicodeTableError {
    IoError { error: std::io::Error }, 
-   ParserError { error: pest::error::Error<Rule> },
-   InvalidCodepoint { codepoint: u32 },
+   ParserError { error: Box<pest::error::Error<Rule>> },
+   InvalidCodepoint { codepoint: String },
    ParseIntError { inner: ParseIntError },
+   InvalidUtf8 { offset: usize },
+   OverlappingGroups { grapheme: String, first_index: usize, second_index: usize },
+   UnimapParserError { error: Box<pest::error::Error<crate::unimap::Rule>> },
+   InvalidUnimapRange { start: u32, end: u32, line: usize },
+   MissingUnimapIndex { index: u32 },
+   GlyphIndexCollision { index: u32 },
+   ConflictingGlyphIndices { first: u32, second: u32 },
+   /// A pinned glyph index at or beyond `limit`, the glyph count or the largest index allowed.
+   GlyphIndexOutOfRange { index: u32, limit: u32 },
+   InvertedRange { start: u32, end: u32 },
+   IncludeCycle { path: std::path::PathBuf },
 }
 
 impl From<ParseIntError> for UnicodeTableError {
@@ -53,7 +84,13 @@ impl From<ParseIntError> for UnicodeTableError {
 
 impl From<pest::error::Error<Rule>> for UnicodeTableError {
     fn from(e: pest::error::Error<Rule>) -> UnicodeTableError {
-        return UnicodeTableError::ParserError{error: e};
+        return UnicodeTableError::ParserError{error: Box::new(e)};
+    }
+}
+
+impl From<pest::error::Error<crate::unimap::Rule>> for UnicodeTableError {
+    fn from(e: pest::error::Error<crate::unimap::Rule>) -> UnicodeTableError {
+        return UnicodeTableError::UnimapParserError{error: Box::new(e)};
     }
 }
 
@@ -68,8 +105,29 @@ impl Display for UnicodeTableError {
         match self {
             UnicodeTableError::IoError{error} => write!(f, "I/O Error while reading Unicode table file: {:?}", error),
             UnicodeTableError::ParserError{error} => write!(f, "Error parsing Unicode table file: \n{:?}", error),
-            UnicodeTableError::InvalidCodepoint{codepoint} => write!(f, "U+{:x} is an invalid Unicode codepoint.", codepoint),
+            UnicodeTableError::InvalidCodepoint{codepoint} => write!(f, "U+{} is an invalid Unicode codepoint.", codepoint),
             UnicodeTableError::ParseIntError{inner} => write!(f, "Error parsing integer: {:?}", inner),
+            UnicodeTableError::InvalidUtf8{offset} => 
+                write!(f, "Unicode table file is not valid UTF-8: invalid byte sequence at offset {}.", offset),
+            UnicodeTableError::OverlappingGroups{grapheme, first_index, second_index} => 
+                write!(f, "Unicode table entries {} and {} both contain {:?}; \
+                use --merge-equiv to merge them into one glyph.", first_index, second_index, grapheme),
+            UnicodeTableError::UnimapParserError{error} => write!(f, "Error parsing unimap file: \n{}", error),
+            UnicodeTableError::InvalidUnimapRange{start, end, line} => 
+                write!(f, "Line {} of unimap file: glyph index range 0x{:x}-0x{:x} must map to `idem` or to a \
+                codepoint range of the same length.", line, start, end),
+            UnicodeTableError::MissingUnimapIndex{index} => 
+                write!(f, "Unimap file has no entry for glyph index 0x{:x}; indices must be contiguous.", index),
+            UnicodeTableError::GlyphIndexCollision{index} => 
+                write!(f, "More than one Unicode table entry is pinned to glyph index {}.", index),
+            UnicodeTableError::ConflictingGlyphIndices{first, second} => 
+                write!(f, "Merged Unicode table entries are pinned to different glyph indices {} and {}.", first, second),
+            UnicodeTableError::GlyphIndexOutOfRange{index, limit} => 
+                write!(f, "A Unicode table entry is pinned to glyph index {}, but the font only has room for {} glyphs.", index, limit),
+            UnicodeTableError::InvertedRange{start, end} => 
+                write!(f, "Unicode table range U+{:04X}..U+{:04X} ends before it starts.", start, end),
+            UnicodeTableError::IncludeCycle{path} => 
+                write!(f, "Unicode table file {} includes itself.", path.display()),
         }
     }
 }
@@ -80,6 +138,9 @@ impl std::error::Error for UnicodeTableError {}
 pub enum TtfParserError {
    IoError { error: std::io::Error }, 
    FontCreationError { error: InvalidFont },
+   NoFittingHeight { max_height: u32 },
+   /// Fallback font `fallback` (counting from 1) renders a different default cell than the input font.
+   FallbackCellSize { fallback: usize, height: u32, width: u32, expected_height: u32, expected_width: u32 },
 }
 
 impl From<std::io::Error> for TtfParserError {
@@ -99,6 +160,11 @@ impl Display for TtfParserError {
         match self {
             TtfParserError::IoError{error} => write!(f, "I/O Error while reading TTF file: {:?}", error),
             TtfParserError::FontCreationError{error} => write!(f, "Error parsing TTF file: \n{:?}", error),
+            TtfParserError::NoFittingHeight{max_height} => 
+                write!(f, "No height up to {} px fits every glyph's outline inside its canvas.", max_height),
+            TtfParserError::FallbackCellSize{fallback, height, width, expected_height, expected_width} => 
+                write!(f, "Fallback font {} renders {} x {} px cells, but the input font renders {} x {} px cells. \
+                    Use --pad or --pad-to-mode-size to give every glyph the same size.", fallback, height, width, expected_height, expected_width),
         }
     }
 }
@@ -109,6 +175,8 @@ impl std::error::Error for TtfParserError {}
 pub enum GlyphSetError {
     InconsistentDimensions { height: u32, width: u32, expected_height: u32, expected_width: u32 },
     InconsistentLengths { length: usize, expected_length: usize },
+    /// Unicode table entries whose graphemes the font (and its fallbacks) do not define.
+    UndefinedGraphemes { graphemes: Vec<String> },
     FromGlyphError{ inner: GlyphError }
 }
 
@@ -127,10 +195,219 @@ impl Display for GlyphSetError {
             GlyphSetError::InconsistentLengths{length, expected_length} => 
                 write!(f, "Glyphs in glyph set do not all have the same length: \
                 glyphs so far were {} bytes, but current glyph is {} bytes.", expected_length, length),
+            GlyphSetError::UndefinedGraphemes{graphemes} => {
+                let listed = graphemes.iter().take(UNDEFINED_GRAPHEMES_LISTED)
+                    .map(|g| match g.chars().exactly_one() {
+                        Ok(c) => char_name(c),
+                        Err(_) => format!("{:?}", g),
+                    })
+                    .join(", ");
+                write!(f, "{} Unicode table entries are not defined in the font: {}", graphemes.len(), listed)?;
+                if graphemes.len() > UNDEFINED_GRAPHEMES_LISTED {
+                    write!(f, ", and {} more", graphemes.len() - UNDEFINED_GRAPHEMES_LISTED)?;
+                }
+                write!(f, ". Check the table for typos, or use --allow-missing to render them anyway.")
+            }
             GlyphSetError::FromGlyphError{inner} => 
-                write!(f, "While constructing glyph set, encountered glyph error: {:?}", inner),
+                write!(f, "While constructing glyph set, encountered glyph error: {}", inner),
         }
     }
 }
 
 impl std::error::Error for GlyphSetError {}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    FromTtfParserError { inner: TtfParserError },
+    FromGlyphSetError { inner: GlyphSetError },
+}
+
+impl From<TtfParserError> for ConvertError {
+    fn from(inner: TtfParserError) -> ConvertError {
+        return ConvertError::FromTtfParserError{inner}
+    }
+}
+
+impl From<GlyphSetError> for ConvertError {
+    fn from(inner: GlyphSetError) -> ConvertError {
+        return ConvertError::FromGlyphSetError{inner}
+    }
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ConvertError::FromTtfParserError{inner} => write!(f, "While loading font: {}", inner),
+            ConvertError::FromGlyphSetError{inner} => write!(f, "While rendering glyphs: {}", inner),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+#[derive(Debug)]
+pub enum Psf2ReadError {
+    TooShort { length: usize, expected_length: usize },
+    BadMagic,
+    UnsupportedVersion { version: u32 },
+    BadHeaderSize { header_size: usize },
+    BadGlyphSize { glyph_size: u32, expected_glyph_size: u64, height: u32, width: u32 },
+    TruncatedUnicodeTable { glyph_index: usize },
+    InvalidUnicodeTable { glyph_index: usize },
+    FromGlyphSetError { inner: GlyphSetError },
+}
+
+impl From<GlyphSetError> for Psf2ReadError {
+    fn from(inner: GlyphSetError) -> Psf2ReadError {
+        return Psf2ReadError::FromGlyphSetError{inner}
+    }
+}
+
+impl Display for Psf2ReadError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Psf2ReadError::TooShort{length, expected_length} => 
+                write!(f, "PSF2 file is too short: expected at least {} bytes, but file was {} bytes.", 
+                expected_length, length),
+            Psf2ReadError::BadMagic => write!(f, "Not a PSF2 file: the magic bytes are missing."),
+            Psf2ReadError::UnsupportedVersion{version} => 
+                write!(f, "Unsupported PSF2 version {}: only version 0 is defined.", version),
+            Psf2ReadError::BadHeaderSize{header_size} => 
+                write!(f, "PSF2 header size is {} bytes, but a PSF2 header is at least 32 bytes.", header_size),
+            Psf2ReadError::BadGlyphSize{glyph_size, expected_glyph_size, height, width} => 
+                write!(f, "PSF2 glyph size is {} bytes, but {} x {} px glyphs take {} bytes.", 
+                glyph_size, height, width, expected_glyph_size),
+            Psf2ReadError::TruncatedUnicodeTable{glyph_index} => 
+                write!(f, "PSF2 Unicode table ends before the entry for glyph {}.", glyph_index),
+            Psf2ReadError::InvalidUnicodeTable{glyph_index} => 
+                write!(f, "PSF2 Unicode table entry for glyph {} is not valid UTF-8.", glyph_index),
+            Psf2ReadError::FromGlyphSetError{inner} => 
+                write!(f, "While reading PSF2 glyphs, encountered glyph set error: {:?}", inner),
+        }
+    }
+}
+
+impl std::error::Error for Psf2ReadError {}
+
+#[derive(Debug)]
+pub enum Psf2TextError {
+    MissingField { name: &'static str },
+    InvalidLine { line: usize, text: String, expected: &'static str },
+    InvalidGlyph { glyph_index: usize },
+    FromUnicodeTableError { inner: UnicodeTableError },
+    FromGlyphSetError { inner: GlyphSetError },
+}
+
+impl From<UnicodeTableError> for Psf2TextError {
+    fn from(inner: UnicodeTableError) -> Psf2TextError {
+        return Psf2TextError::FromUnicodeTableError{inner}
+    }
+}
+
+impl From<GlyphSetError> for Psf2TextError {
+    fn from(inner: GlyphSetError) -> Psf2TextError {
+        return Psf2TextError::FromGlyphSetError{inner}
+    }
+}
+
+impl Display for Psf2TextError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Psf2TextError::MissingField{name} => write!(f, "Debug text is missing a valid `{}` line.", name),
+            Psf2TextError::InvalidLine{line, text, expected} => 
+                write!(f, "Line {} of debug text: expected `{}`, but found {:?}.", line, expected, text),
+            Psf2TextError::InvalidGlyph{glyph_index} => 
+                write!(f, "Glyph {} in debug text may only contain `#` and `.`.", glyph_index),
+            Psf2TextError::FromUnicodeTableError{inner} => write!(f, "In debug text glyph list: {}", inner),
+            Psf2TextError::FromGlyphSetError{inner} => 
+                write!(f, "While reading debug text glyphs, encountered glyph set error: {:?}", inner),
+        }
+    }
+}
+
+impl std::error::Error for Psf2TextError {}
+
+#[derive(Debug)]
+pub enum Psf1ReadError {
+    TooShort { length: usize, expected_length: usize },
+    BadMagic,
+    TruncatedUnicodeTable { glyph_index: usize },
+    InvalidUnicodeTable { glyph_index: usize },
+}
+
+impl Display for Psf1ReadError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Psf1ReadError::TooShort{length, expected_length} => 
+                write!(f, "PSF1 file is too short: expected at least {} bytes, but file was {} bytes.", 
+                expected_length, length),
+            Psf1ReadError::BadMagic => write!(f, "Not a PSF1 file: the magic bytes are missing."),
+            Psf1ReadError::TruncatedUnicodeTable{glyph_index} => 
+                write!(f, "PSF1 Unicode table ends before the entry for glyph {}.", glyph_index),
+            Psf1ReadError::InvalidUnicodeTable{glyph_index} => 
+                write!(f, "PSF1 Unicode table entry for glyph {} contains a surrogate codepoint.", glyph_index),
+        }
+    }
+}
+
+impl std::error::Error for Psf1ReadError {}
+
+#[derive(Debug)]
+pub enum Psf1WriteError {
+    WrongWidth { width: u32 },
+    TooTall { height: u32, max_height: u32 },
+    TooManyGlyphs { count: usize, max_count: usize },
+    NotUcs2 { grapheme: String },
+}
+
+impl Display for Psf1WriteError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Psf1WriteError::WrongWidth{width} => 
+                write!(f, "PSF1 only supports glyphs 8 px wide, but the glyphs are {} px wide. \
+                Try another height or --cell-width, or use --format psf2.", width),
+            Psf1WriteError::TooTall{height, max_height} => 
+                write!(f, "Glyphs are {} px tall, but PSF1 allows at most {} px.", height, max_height),
+            Psf1WriteError::TooManyGlyphs{count, max_count} => 
+                write!(f, "The font has {} glyphs, but PSF1 allows at most {}.", count, max_count),
+            Psf1WriteError::NotUcs2{grapheme} => 
+                write!(f, "The Unicode table maps {:?}, but PSF1 tables only hold codepoints up to U+FFFF.", grapheme),
+        }
+    }
+}
+
+impl std::error::Error for Psf1WriteError {}
+
+#[derive(Debug)]
+pub enum ProportionalFontError {
+    InconsistentHeights { height: u32, expected_height: u32 },
+    TooWide { width: u32, max_width: u32 },
+    TooShort { length: usize, expected_length: usize },
+    BadMagic,
+    FromPsf2ReadError { inner: Psf2ReadError },
+}
+
+impl From<Psf2ReadError> for ProportionalFontError {
+    fn from(inner: Psf2ReadError) -> ProportionalFontError {
+        return ProportionalFontError::FromPsf2ReadError{inner}
+    }
+}
+
+impl Display for ProportionalFontError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            ProportionalFontError::InconsistentHeights{height, expected_height} => 
+                write!(f, "Glyphs in a proportional font must share a height: \
+                glyphs so far were {} px tall, but current glyph is {} px tall.", expected_height, height),
+            ProportionalFontError::TooWide{width, max_width} => 
+                write!(f, "Glyph is {} px wide, but a proportional font allows at most {} px.", width, max_width),
+            ProportionalFontError::TooShort{length, expected_length} => 
+                write!(f, "Proportional font file is too short: expected at least {} bytes, but file was {} bytes.", 
+                expected_length, length),
+            ProportionalFontError::BadMagic => write!(f, "Not a proportional font file: the magic bytes are missing."),
+            ProportionalFontError::FromPsf2ReadError{inner} => write!(f, "In proportional font Unicode table: {}", inner),
+        }
+    }
+}
+
+impl std::error::Error for ProportionalFontError {}
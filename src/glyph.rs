@@ -2,20 +2,66 @@ use crate::errors::GlyphError;
 use ab_glyph::v2::GlyphImage;
 use ab_glyph::GlyphImageFormat;
 use bitvec::prelude::*;
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 
 /// A glyph bitmap, in psf2 style: mono-color, one bit per pixel, byte-padded rows.
+#[derive(Clone)]
 pub struct Glyph {
     pub height: u32,
     pub width: u32,
     pub data: Vec<u8>,
     pub grapheme: String,
+    /// The horizontal distance in pixels from the glyph origin to the left edge of its ink, if
+    /// known. Only needed by proportional export formats; PSF2 ignores it.
+    pub bearing_x: Option<i32>,
+    /// The vertical distance in pixels from the baseline up to the top edge of its ink, if known.
+    /// Only needed by proportional export formats; PSF2 ignores it.
+    pub bearing_y: Option<i32>,
+    /// The horizontal distance in pixels from the glyph origin to the left edge of the bitmap, if
+    /// known. `None` means the bitmap starts at the origin. Only needed by proportional export
+    /// formats; PSF2 ignores it.
+    pub x_offset: Option<i32>,
+    /// The vertical distance in pixels from the baseline up to the bottom edge of the bitmap, if
+    /// known: rasterized outlines and embedded bitmaps have one, which follows the bitmap when its
+    /// canvas is resized. Only needed by proportional export formats; PSF2 ignores it.
+    pub y_offset: Option<i32>,
+    /// The pixels-per-em size the glyph was rendered at. For embedded bitmaps, this is the PPEM
+    /// of the strike the bitmap came from, which may differ from the requested height.
+    pub ppem: u32,
 }
 
 impl Glyph {
+    /// Creates a glyph of the given dimensions with no pixels set.
+    pub fn blank(height: u32, width: u32, grapheme: String, ppem: u32) -> Self {
+        let row_length = (width as f64 / 8.0).ceil() as usize;
+        let data = vec![0u8; row_length * height as usize];
+        return Self{height, width, data, grapheme, bearing_x: None, bearing_y: None, x_offset: None, y_offset: None, ppem};
+    }
+
+    /// Creates a glyph of the given dimensions containing a one-pixel rectangle outline, inset by
+    /// one pixel from each edge where the canvas is large enough. Used to mark missing characters.
+    pub fn outline_box(height: u32, width: u32, grapheme: String, ppem: u32) -> Self {
+        let row_length = (width as f64 / 8.0).ceil() as usize;
+        let mut data = bitvec![u8, Msb0; 0; row_length * 8 * height as usize];
+        let (left, right) = if width > 2 {(1, width - 2)} else {(0, width.saturating_sub(1))};
+        let (top, bottom) = if height > 2 {(1, height - 2)} else {(0, height.saturating_sub(1))};
+        if width > 0 && height > 0 {
+            for y in top..=bottom {
+                for x in left..=right {
+                    if x == left || x == right || y == top || y == bottom {
+                        data.set(x as usize + y as usize * row_length * 8, true);
+                    }
+                }
+            }
+        }
+        return Self{height, width, data: data.into_vec(), grapheme, bearing_x: None, bearing_y: None, x_offset: None, y_offset: None, ppem};
+    }
+
     /// Combines `self` and `other`'s bitmaps with a logical OR, and appends `other`'s grapheme to
     /// `self`'s, in a new returned `Glyph` struct. Intended for adding combining diacritics.
     /// Returns an error if the heights, widths, or lengths of `self` and `other` do not match.
+    #[allow(clippy::should_implement_trait)]
     pub fn add(self, other: Self) -> Result<Self, GlyphError> {
         if self.height != other.height || self.width != other.width {
             return Err(GlyphError::WrongDimensions{
@@ -35,40 +81,419 @@ impl Glyph {
 
         // bitwise OR the bytes of self's and other's data: this "overlays" the bitmaps on top of
         // each other.
-        let data = self.data.into_iter().zip(other.data.into_iter())
+        let data = self.data.into_iter().zip(other.data)
             .map( |(a,b)| a | b )
             .collect::<Vec<_>>();
 
         let height = self.height;
         let width = self.width;
+        let bearing_x = self.bearing_x;
+        let bearing_y = self.bearing_y;
+        let x_offset = self.x_offset;
+        let y_offset = self.y_offset;
+        let ppem = self.ppem;
+
+        return Ok(Self{height, width, data, grapheme, bearing_x, bearing_y, x_offset, y_offset, ppem})
+    }
 
-        return Ok(Self{height, width, data, grapheme})
+    /// Like `add`, but if `self` and `other` have different dimensions, first pads each to the
+    /// larger height and width, with blank space to the right and below. Intended for combining
+    /// characters whose advance widths differ slightly from their base character's.
+    pub fn overlay(self, other: Self) -> Result<Self, GlyphError> {
+        let height = std::cmp::max(self.height, other.height);
+        let width = std::cmp::max(self.width, other.width);
+        let base = self.pad(height, width)?;
+        let other = other.pad(height, width)?;
+        return base.add(other);
+    }
+
+    /// ORs `other`'s bitmap into `self`'s with `other`'s top-left corner at column `x` and row `y`
+    /// of `self`, and appends `other`'s grapheme to `self`'s. Pixels that land outside `self`'s
+    /// canvas are lost. Intended for placing combining marks at their own position.
+    pub fn overlay_at(mut self, other: Self, x: i64, y: i64) -> Self {
+        for other_y in 0..other.height {
+            for other_x in 0..other.width {
+                let (new_x, new_y) = (other_x as i64 + x, other_y as i64 + y);
+                let inside = new_x >= 0 && new_y >= 0 && new_x < self.width as i64 && new_y < self.height as i64;
+                if inside && other.get_pixel(other_x, other_y) {
+                    self.set_pixel(new_x as u32, new_y as u32, true);
+                }
+            }
+        }
+        self.grapheme.push_str(&other.grapheme);
+        return self;
     }
 
     /// Pads `self` to given dimensions `new_height` and `new_width`. Inserts blank space to the
     /// right of `self` and below it. Returns an error if the padded dimensions are too small to
     /// fit `self`.
     pub fn pad(self, new_height: u32, new_width: u32) -> Result<Self, GlyphError> {
+        return self.pad_anchored(new_height, new_width, HAnchor::Left, VAnchor::Top);
+    }
+
+    /// Pads `self` to given dimensions `new_height` and `new_width`, placing it in the padded
+    /// canvas according to `h_anchor` and `v_anchor`. Returns an error if the padded dimensions are
+    /// too small to fit `self`.
+    pub fn pad_anchored(self, new_height: u32, new_width: u32, h_anchor: HAnchor, v_anchor: VAnchor) 
+        -> Result<Self, GlyphError> {
         if self.height > new_height || self.width > new_width {
             return Err(GlyphError::PadTooSmall{height: self.height, width: self.width, pad_height: new_height, pad_width: new_width});
         }
-        let mut data: Vec<u8> = vec![];
-        let self_row_length = (self.width as f64 / 8.0).ceil() as usize;
+        return Ok(self.resize_canvas(new_height, new_width, h_anchor, v_anchor));
+    }
+
+    /// Resizes `self`'s canvas to `new_height` x `new_width`, keeping the bitmap anchored to the
+    /// given edges (or centered). Dimensions larger than the current canvas are padded with blank
+    /// space; dimensions smaller than the current canvas are cropped, with a warning if any
+    /// set pixels are lost.
+    pub fn resize_canvas(self, new_height: u32, new_width: u32, h_anchor: HAnchor, v_anchor: VAnchor) -> Self {
+        let x_offset = h_anchor.offset(self.width, new_width);
+        let y_offset = v_anchor.offset(self.height, new_height);
         let padded_row_length = (new_width as f64 / 8.0).ceil() as usize;
-        if self_row_length < padded_row_length {
-            for chunk in self.data.chunks_exact(self_row_length.into()) {
-                data.append(&mut (chunk.to_owned().to_vec()));
-                data.append(&mut vec![0u8; padded_row_length - self_row_length]);
+
+        let mut data = bitvec![u8, Msb0; 0; padded_row_length * 8 * new_height as usize];
+        let mut lost_pixels = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !self.get_pixel(x, y) {
+                    continue;
+                }
+                let new_x = x as i64 + x_offset;
+                let new_y = y as i64 + y_offset;
+                if new_x < 0 || new_y < 0 || new_x >= new_width as i64 || new_y >= new_height as i64 {
+                    lost_pixels += 1;
+                    continue;
+                }
+                data.set(new_x as usize + new_y as usize * padded_row_length * 8, true);
+            }
+        }
+        // rows added below the bitmap move its bottom edge down; rows cropped from below move it up.
+        let rows_below = new_height as i64 - self.height as i64 - y_offset;
+        // columns added on the left move its left edge left of where it was, e.g. of the origin.
+        let left_edge = match x_offset {
+            0 => self.x_offset,
+            _ => Some((self.x_offset.unwrap_or(0) as i64 - x_offset) as i32),
+        };
+
+        if lost_pixels > 0 {
+            log::warn!("While resizing glyph for {:?} to {} x {} px: {} set pixels were cropped.",
+                self.grapheme, new_height, new_width, lost_pixels);
+        }
+
+        return Self{
+            height: new_height, 
+            width: new_width, 
+            data: data.into_vec(), 
+            grapheme: self.grapheme, 
+            bearing_x: self.bearing_x, 
+            bearing_y: self.bearing_y,
+            x_offset: left_edge,
+            y_offset: self.y_offset.map(|o| (o as i64 - rows_below) as i32),
+            ppem: self.ppem,
+        };
+    }
+
+    /// Removes the blank columns on the left and right of the bitmap. Returns the trimmed glyph and
+    /// the number of columns removed from the left and from the right. Blank glyphs are returned
+    /// unchanged.
+    pub fn trim(self) -> (Self, u32, u32) {
+        let ink_columns: Vec<u32> = (0..self.width).filter(|x| (0..self.height).any(|y| self.get_pixel(*x, y))).collect();
+        let (Some(&first), Some(&last)) = (ink_columns.first(), ink_columns.last()) else {
+            return (self, 0, 0);
+        };
+        let (height, width) = (self.height, self.width);
+        let trimmed = self
+            .resize_canvas(height, last + 1, HAnchor::Left, VAnchor::Top)
+            .resize_canvas(height, last + 1 - first, HAnchor::Right, VAnchor::Top);
+        return (trimmed, first, width - 1 - last);
+    }
+
+    /// Scales `self` to `new_height` x `new_width` by sampling one source pixel for each new
+    /// pixel. Only used for `--strike-height scale`, where shrinking a strike by ORing its pixels
+    /// together, as `scale_nearest` does, would fill in the counters of small letters; everything
+    /// else should use `scale_nearest`. A glyph with no pixels scales to a blank one.
+    pub(crate) fn scale(self, new_height: u32, new_width: u32) -> Self {
+        if self.height == 0 || self.width == 0 {
+            return Self{x_offset: None, y_offset: None, ..Self::blank(new_height, new_width, self.grapheme, self.ppem)};
+        }
+        let padded_row_length = (new_width as f64 / 8.0).ceil() as usize;
+        let mut data = bitvec![u8, Msb0; 0; padded_row_length * 8 * new_height as usize];
+        for y in 0..new_height {
+            for x in 0..new_width {
+                let source_x = (x as u64 * self.width as u64 / new_width as u64) as u32;
+                let source_y = (y as u64 * self.height as u64 / new_height as u64) as u32;
+                if self.get_pixel(source_x, source_y) {
+                    data.set(x as usize + y as usize * padded_row_length * 8, true);
+                }
+            }
+        }
+        return Self{
+            height: new_height, 
+            width: new_width, 
+            data: data.into_vec(), 
+            grapheme: self.grapheme, 
+            bearing_x: self.bearing_x, 
+            bearing_y: self.bearing_y,
+            x_offset: self.x_offset
+                .map(|o| (o as f64 * new_width as f64 / self.width as f64).round() as i32),
+            y_offset: self.y_offset
+                .map(|o| (o as f64 * new_height as f64 / self.height as f64).round() as i32),
+            ppem: self.ppem,
+        };
+    }
+
+    /// Scales `self` to `new_height` x `new_width`, setting each new pixel if any of the source
+    /// pixels it covers is set. Enlarging copies each source pixel into a block (exactly, for
+    /// integer factors); shrinking ORs each block of source pixels together, so that one-pixel
+    /// strokes survive. A glyph with no pixels, e.g. 0 pixels wide, scales to a blank one.
+    pub fn scale_nearest(self, new_height: u32, new_width: u32) -> Self {
+        if self.height == 0 || self.width == 0 {
+            return Self{x_offset: None, y_offset: None, ..Self::blank(new_height, new_width, self.grapheme, self.ppem)};
+        }
+        // the half-open range of source pixels that new pixel `i` of `new` covers, out of `old`.
+        let span = |i: u32, old: u32, new: u32| {
+            let start = (i as u64 * old as u64 / new as u64) as u32;
+            let end = ((i as u64 + 1) * old as u64 / new as u64) as u32;
+            start..std::cmp::max(end, start + 1)
+        };
+        let (height, width) = (self.height, self.width);
+        let mut scaled = Self::blank(new_height, new_width, self.grapheme.clone(), self.ppem);
+        for y in 0..new_height {
+            for x in 0..new_width {
+                let set = span(y, height, new_height)
+                    .flat_map(|source_y| span(x, width, new_width).map(move |source_x| (source_x, source_y)))
+                    .any(|(source_x, source_y)| self.get_pixel(source_x, source_y));
+                scaled.set_pixel(x, y, set);
+            }
+        }
+        return Self{
+            bearing_x: self.bearing_x,
+            bearing_y: self.bearing_y,
+            x_offset: self.x_offset
+                .map(|o| (o as f64 * new_width as f64 / width as f64).round() as i32),
+            y_offset: self.y_offset
+                .map(|o| (o as f64 * new_height as f64 / height as f64).round() as i32),
+            ..scaled
+        };
+    }
+
+    /// Halves the height of the bitmap by combining each pair of rows with a logical OR, so that
+    /// one-pixel horizontal strokes survive. An odd last row is kept on its own.
+    pub fn squeeze_vertical(self) -> Self {
+        let height = self.height.div_ceil(2);
+        let padded_row_length = (self.width as f64 / 8.0).ceil() as usize;
+        let data: Vec<u8> = self.data.chunks(padded_row_length * 2)
+            .flat_map(|pair| {
+                let (top, bottom) = pair.split_at(std::cmp::min(padded_row_length, pair.len()));
+                top.iter().enumerate().map(|(i, byte)| byte | bottom.get(i).copied().unwrap_or(0)).collect::<Vec<u8>>()
+            })
+            .collect();
+        return Self{
+            height,
+            data,
+            y_offset: self.y_offset.map(|o| o.div_euclid(2)),
+            ..self
+        };
+    }
+
+    /// Thickens strokes by setting every pixel whose left neighbor is set.
+    pub fn embolden(self) -> Self {
+        return self.map_pixels(|g, x, y| g.pixel_or_blank(x, y) || g.pixel_or_blank(x - 1, y));
+    }
+
+    /// Shears the bitmap into a faux italic, pivoting on the bottom row: each row is shifted right
+    /// by `round(slope * n)` pixels, where `n` is how many rows it lies above the bottom row. A
+    /// negative slope leans the glyph left. The canvas is widened by the largest shift, so no
+    /// pixels are lost and glyphs of equal height stay equally wide.
+    pub fn shear(self, slope: f32) -> Self {
+        let bottom = self.height.saturating_sub(1);
+        let extra_width = (slope.abs() * bottom as f32).round() as u32;
+        // a left lean moves the top rows left, so the bottom row starts `extra_width` in.
+        let base_shift = if slope < 0.0 {extra_width as i64} else {0};
+        let (height, width) = (self.height, self.width + extra_width);
+        return self.resize_canvas(height, width, HAnchor::Left, VAnchor::Top).map_pixels(|g, x, y| {
+            let shift = base_shift + (slope * (bottom as i64 - y) as f32).round() as i64;
+            g.pixel_or_blank(x - shift, y)
+        });
+    }
+
+    /// Replaces the ink with a one-pixel outline: sets every blank pixel that borders a set pixel
+    /// horizontally or vertically, and clears every set pixel.
+    pub fn outline(self) -> Self {
+        return self.map_pixels(|g, x, y| !g.pixel_or_blank(x, y) 
+            && [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| g.pixel_or_blank(x + dx, y + dy)));
+    }
+
+    /// Swaps set and blank pixels. Only the `width` real columns of each row are flipped; the
+    /// padding bits past them stay blank, so inverting twice gives back the original bitmap.
+    pub fn invert(self) -> Self {
+        return self.map_pixels(|g, x, y| !g.pixel_or_blank(x, y));
+    }
+
+    /// Mirrors the bitmap left to right, within the `width` real columns of each row.
+    pub fn flip_horizontal(self) -> Self {
+        let right = self.width as i64 - 1;
+        return self.map_pixels(|g, x, y| g.pixel_or_blank(right - x, y));
+    }
+
+    /// Mirrors the bitmap top to bottom.
+    pub fn flip_vertical(self) -> Self {
+        let bottom = self.height as i64 - 1;
+        return self.map_pixels(|g, x, y| g.pixel_or_blank(x, bottom - y));
+    }
+
+    /// Draws a horizontal line across the glyph's width at row `row`, counted from the top, as an
+    /// underline. A row below the bottom of the canvas draws nothing.
+    pub fn with_underline(self, row: u32) -> Self {
+        return self.with_horizontal_line(row);
+    }
+
+    /// Draws a horizontal line across the glyph's width at row `row`, counted from the top, as a
+    /// strikethrough. A row below the bottom of the canvas draws nothing.
+    pub fn with_strikethrough(self, row: u32) -> Self {
+        return self.with_horizontal_line(row);
+    }
+
+    /// Sets every pixel in row `row` of the glyph's real width, leaving the padding bits blank.
+    fn with_horizontal_line(self, row: u32) -> Self {
+        return self.map_pixels(|g, x, y| y == row as i64 || g.pixel_or_blank(x, y));
+    }
+
+    /// Builds a bitmap of the same size, where each pixel is set if `f(self, x, y)` is true.
+    fn map_pixels(self, f: impl Fn(&Self, i64, i64) -> bool) -> Self {
+        let padded_row_length = (self.width as f64 / 8.0).ceil() as usize;
+        let mut data = bitvec![u8, Msb0; 0; padded_row_length * 8 * self.height as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if f(&self, x as i64, y as i64) {
+                    data.set(x as usize + y as usize * padded_row_length * 8, true);
+                }
+            }
+        }
+        return Self{data: data.into_vec(), ..self};
+    }
+
+    /// Like `get_pixel`, but treats pixels outside the canvas as blank.
+    fn pixel_or_blank(&self, x: i64, y: i64) -> bool {
+        return x >= 0 && y >= 0 && x < self.width as i64 && y < self.height as i64 && self.get_pixel(x as u32, y as u32);
+    }
+
+    /// A fast hash of the glyph's dimensions and bitmap, ignoring its grapheme and metadata. Glyphs
+    /// that look identical have the same digest; glyphs with the same digest should still be
+    /// compared in full before being treated as identical.
+    pub fn bitmap_digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.height, self.width, &self.data).hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    /// Counts the pixels that are set in exactly one of `self` and `other`, comparing the two
+    /// bitmaps from their top-left corners. Pixels outside one glyph's canvas count as blank.
+    pub fn pixel_difference(&self, other: &Self) -> u32 {
+        let height = std::cmp::max(self.height, other.height);
+        let width = std::cmp::max(self.width, other.width);
+        let set = |g: &Self, x: u32, y: u32| x < g.width && y < g.height && g.get_pixel(x, y);
+        return (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| set(self, *x, *y) != set(other, *x, *y))
+            .count() as u32;
+    }
+
+    /// Describes the bitmap as run-length-encoded spans, one line per row. Each span is a count
+    /// followed by `.` for blank pixels or `#` for set pixels, e.g. `2.3#3.`.
+    pub fn to_rle_string(&self) -> String {
+        let mut rows: Vec<String> = vec![];
+        for y in 0..self.height {
+            let mut row = String::new();
+            let mut x = 0;
+            while x < self.width {
+                let value = self.get_pixel(x, y);
+                let run_start = x;
+                while x < self.width && self.get_pixel(x, y) == value {
+                    x += 1;
+                }
+                row.push_str(&format!("{}{}", x - run_start, if value {'#'} else {'.'}));
+            }
+            rows.push(row);
+        }
+        return rows.join("\n");
+    }
+
+    /// Draws the bitmap as ASCII art, one line per row, with `#` for set pixels and `.` for blank
+    /// pixels.
+    pub fn to_ascii_art(&self) -> String {
+        return self.rows()
+            .map(|row| row.iter().map(|bit| if *bit {'#'} else {'.'}).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+
+    /// Parses a bitmap drawn by `to_ascii_art`, given as one string per row. Returns `None` if the
+    /// rows differ in width or contain anything other than `#` and `.`.
+    pub fn from_ascii_art(rows: &[&str], grapheme: String, ppem: u32) -> Option<Self> {
+        let width = rows.first().map_or(0, |row| row.chars().count()) as u32;
+        let mut glyph = Self::blank(rows.len() as u32, width, grapheme, ppem);
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width as usize {
+                return None;
+            }
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '#' => glyph.set_pixel(x as u32, y as u32, true),
+                    '.' => (),
+                    _ => return None,
+                }
+            }
+        }
+        return Some(glyph);
+    }
+
+    /// Clears every bit at or beyond `width` in each byte-padded row, which should never be set.
+    pub fn clear_padding_bits(&mut self) {
+        let padded_row_length = (self.width as f64 / 8.0).ceil() as usize;
+        if padded_row_length == 0 {
+            return;
+        }
+        for row in self.data.chunks_mut(padded_row_length) {
+            if let Some(padding) = row.view_bits_mut::<Msb0>().get_mut(self.width as usize..) {
+                padding.fill(false);
             }
-        } else {
-            data = self.data;
         }
-        data.append(&mut vec![0u8; (new_height - self.height) as usize * padded_row_length]);
-        return Ok(Self{height: new_height, width: new_width, data, grapheme: self.grapheme});
     }
 
-    /// Creates a new `Glyph` from an embedded bitmap in a TTF/OTF file.
-    pub fn from_glyph_image(glyph_image: GlyphImage, grapheme: char) -> Result<Self, GlyphError> {
+    /// Returns whether the pixel at column `x` and row `y` is set. Panics if the pixel is outside
+    /// the canvas, including the padding bits at the end of each row.
+    pub fn get_pixel(&self, x: u32, y: u32) -> bool {
+        assert!(x < self.width && y < self.height, "pixel ({}, {}) is outside the {}x{} glyph", x, y, self.width, self.height);
+        let row_length = (self.width as f64 / 8.0).ceil() as usize;
+        let byte = self.data[y as usize * row_length + (x / 8) as usize];
+        return byte & (0x80 >> (x % 8)) != 0;
+    }
+
+    /// Sets or clears the pixel at column `x` and row `y`. Panics if the pixel is outside the
+    /// canvas, as `get_pixel` does.
+    pub fn set_pixel(&mut self, x: u32, y: u32, value: bool) {
+        assert!(x < self.width && y < self.height, "pixel ({}, {}) is outside the {}x{} glyph", x, y, self.width, self.height);
+        let row_length = (self.width as f64 / 8.0).ceil() as usize;
+        let byte = &mut self.data[y as usize * row_length + (x / 8) as usize];
+        match value {
+            true => *byte |= 0x80 >> (x % 8),
+            false => *byte &= !(0x80 >> (x % 8)),
+        }
+    }
+
+    /// Iterates over the bitmap's rows from top to bottom, each `width` bits long, without the
+    /// padding bits at the end of each byte-padded row.
+    pub fn rows(&self) -> impl Iterator<Item = &BitSlice<u8, Msb0>> {
+        let row_bits = (self.width as f64 / 8.0).ceil() as usize * 8;
+        let width = self.width as usize;
+        let bits = self.data.view_bits::<Msb0>();
+        return (0..self.height as usize).map(move |y| &bits[y * row_bits..y * row_bits + width]);
+    }
+
+    /// Creates a new `Glyph` from an embedded bitmap in a TTF/OTF file. Grayscale bitmaps are
+    /// flattened to one bit, setting each pixel at least `threshold` of the way to full intensity.
+    pub fn from_glyph_image(glyph_image: GlyphImage, grapheme: &str, threshold: f32) -> Result<Self, GlyphError> {
         return match glyph_image.format {
             GlyphImageFormat::BitmapMono => {
                 Ok(Glyph {
@@ -76,13 +501,18 @@ impl Glyph {
                     width: glyph_image.width as u32,
                     data: glyph_image.data.to_vec(),
                     grapheme: grapheme.to_string(),
+                    bearing_x: None,
+                    bearing_y: None,
+                    x_offset: Some(glyph_image.origin.x as i32),
+                    y_offset: Some(glyph_image.origin.y as i32),
+                    ppem: glyph_image.pixels_per_em as u32,
                 })
             }
 
             GlyphImageFormat::BitmapMonoPacked => {
                 let mut data = bitvec![u8, Msb0; 0; 0];
                 let whitespace_width = ((glyph_image.width as f64 / 8.0).ceil() as usize) * 8 - glyph_image.width as usize;
-                let whitespace = bitvec![u8, Msb0; 0; whitespace_width as usize];
+                let whitespace = bitvec![u8, Msb0; 0; whitespace_width];
                 let mut glyph_image_clone = glyph_image.data.to_vec();
 
                 let glyph_image_rows = glyph_image_clone.view_bits_mut::<Msb0>().chunks_exact_mut(glyph_image.width.into());
@@ -99,9 +529,304 @@ impl Glyph {
                     width: glyph_image.width as u32,
                     data: data_vec,
                     grapheme: grapheme.to_string(),
+                    bearing_x: None,
+                    bearing_y: None,
+                    x_offset: Some(glyph_image.origin.x as i32),
+                    y_offset: Some(glyph_image.origin.y as i32),
+                    ppem: glyph_image.pixels_per_em as u32,
                 })
             }
+            GlyphImageFormat::BitmapGray2 => Ok(Self::from_gray_image(&glyph_image, grapheme, 2, false, threshold)),
+            GlyphImageFormat::BitmapGray2Packed => Ok(Self::from_gray_image(&glyph_image, grapheme, 2, true, threshold)),
+            GlyphImageFormat::BitmapGray4 => Ok(Self::from_gray_image(&glyph_image, grapheme, 4, false, threshold)),
+            GlyphImageFormat::BitmapGray4Packed => Ok(Self::from_gray_image(&glyph_image, grapheme, 4, true, threshold)),
+            GlyphImageFormat::BitmapGray8 => Ok(Self::from_gray_image(&glyph_image, grapheme, 8, false, threshold)),
             _fmt => Err(GlyphError::GlyphImgFmtUnsupported{format: _fmt}),
         }
     }
+
+    /// Flattens a grayscale embedded bitmap with `bits_per_pixel` bits per pixel to one bit. Rows
+    /// of `packed` bitmaps follow on directly from each other; otherwise each row is padded to a
+    /// whole byte.
+    fn from_gray_image(glyph_image: &GlyphImage, grapheme: &str, bits_per_pixel: usize, packed: bool, threshold: f32) -> Self {
+        let (height, width) = (glyph_image.height as usize, glyph_image.width as usize);
+        let row_bits = match packed {
+            true => width * bits_per_pixel,
+            false => (width * bits_per_pixel).div_ceil(8) * 8,
+        };
+        let max_value = ((1u32 << bits_per_pixel) - 1) as f32;
+        let source = glyph_image.data.view_bits::<Msb0>();
+        let padded_row_length = width.div_ceil(8);
+        let mut data = vec![0u8; height * padded_row_length];
+        let bits = data.view_bits_mut::<Msb0>();
+        for y in 0..height {
+            for x in 0..width {
+                let start = y * row_bits + x * bits_per_pixel;
+                // a truncated bitmap reads as blank rather than failing the whole glyph.
+                let value = source.get(start..start + bits_per_pixel).map_or(0, |v| v.load_be::<u8>());
+                bits.set(y * padded_row_length * 8 + x, value as f32 / max_value >= threshold);
+            }
+        }
+        return Glyph {
+            height: height as u32,
+            width: width as u32,
+            data,
+            grapheme: grapheme.to_string(),
+            bearing_x: None,
+            bearing_y: None,
+            x_offset: Some(glyph_image.origin.x as i32),
+            y_offset: Some(glyph_image.origin.y as i32),
+            ppem: glyph_image.pixels_per_em as u32,
+        };
+    }
+}
+
+/// Horizontal placement of a glyph bitmap when its canvas is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HAnchor {
+    Left,
+    /// Centered. When the width changes by an odd number of columns, the bitmap rounds to the
+    /// left: a padded canvas gets the extra blank column on the right.
+    Center,
+    Right,
+}
+
+impl HAnchor {
+    /// The column in the new canvas where the old canvas's left edge is placed. Negative if the
+    /// canvas is being cropped on the left.
+    fn offset(self, width: u32, new_width: u32) -> i64 {
+        let difference = new_width as i64 - width as i64;
+        return match self {
+            HAnchor::Left => 0,
+            HAnchor::Center => difference / 2,
+            HAnchor::Right => difference,
+        }
+    }
+}
+
+/// Vertical placement of a glyph bitmap when its canvas is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VAnchor {
+    Top,
+    /// Centered. When the height changes by an odd number of rows, the bitmap rounds up: a
+    /// padded canvas gets the extra blank row at the bottom.
+    Center,
+    Bottom,
+}
+
+impl VAnchor {
+    /// The row in the new canvas where the old canvas's top edge is placed. Negative if the
+    /// canvas is being cropped on the top.
+    fn offset(self, height: u32, new_height: u32) -> i64 {
+        let difference = new_height as i64 - height as i64;
+        return match self {
+            VAnchor::Top => 0,
+            VAnchor::Center => difference / 2,
+            VAnchor::Bottom => difference,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn art(rows: &[&str]) -> Glyph {
+        return Glyph::from_ascii_art(rows, String::from("x"), rows.len() as u32).unwrap();
+    }
+
+    /// An 8 x 8 'A'.
+    const LETTER_A: [&str; 8] = [
+        "...##...",
+        "..#..#..",
+        ".#....#.",
+        ".#....#.",
+        ".######.",
+        ".#....#.",
+        ".#....#.",
+        "........",
+    ];
+
+    #[test]
+    fn invert_swaps_set_and_blank_pixels() {
+        let inverted = art(&LETTER_A).invert();
+        assert_eq!(inverted.to_ascii_art(), [
+            "###..###",
+            "##.##.##",
+            "#.####.#",
+            "#.####.#",
+            "#......#",
+            "#.####.#",
+            "#.####.#",
+            "########",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn invert_twice_is_identity() {
+        let glyph = art(&LETTER_A);
+        assert_eq!(glyph.clone().invert().invert().data, glyph.data);
+        // padding bits beyond the width stay clear.
+        let narrow = art(&["#..", ".#."]);
+        assert_eq!(narrow.clone().invert().invert().data, narrow.data);
+        assert_eq!(narrow.invert().data, vec![0b0110_0000, 0b1010_0000]);
+    }
+
+    #[test]
+    fn embolden_widens_vertical_stroke_to_two_pixels() {
+        let stroke = art(&["..#..", "..#..", "..#.."]);
+        assert_eq!(stroke.embolden().to_ascii_art(), "..##.\n..##.\n..##.");
+        // the stroke cannot spread past the last column.
+        let edge = art(&["...#", "...#"]);
+        assert_eq!(edge.embolden().to_ascii_art(), "...#\n...#");
+    }
+
+    /// A 3 x 3 glyph with a distinct pattern in each corner.
+    const CORNERS: [&str; 3] = [
+        "#.#",
+        "...",
+        "##.",
+    ];
+
+    #[test]
+    fn resize_canvas_grows_both_dimensions() {
+        let glyph = art(&CORNERS).resize_canvas(5, 4, HAnchor::Right, VAnchor::Center);
+        assert_eq!(glyph.to_ascii_art(), ["....", ".#.#", "....", ".##.", "...."].join("\n"));
+    }
+
+    #[test]
+    fn resize_canvas_grows_height_and_crops_width() {
+        let glyph = art(&CORNERS).resize_canvas(4, 2, HAnchor::Left, VAnchor::Bottom);
+        assert_eq!(glyph.to_ascii_art(), ["..", "#.", "..", "##"].join("\n"));
+    }
+
+    #[test]
+    fn resize_canvas_crops_height_and_grows_width() {
+        let glyph = art(&CORNERS).resize_canvas(2, 5, HAnchor::Center, VAnchor::Top);
+        assert_eq!(glyph.to_ascii_art(), [".#.#.", "....."].join("\n"));
+    }
+
+    #[test]
+    fn resize_canvas_crops_both_dimensions() {
+        let glyph = art(&CORNERS).resize_canvas(2, 2, HAnchor::Right, VAnchor::Bottom);
+        assert_eq!(glyph.to_ascii_art(), ["..", "#."].join("\n"));
+        // padding bits of the narrower rows are left clear.
+        assert_eq!(glyph.data, vec![0, 0b1000_0000]);
+    }
+
+    #[test]
+    fn overlay_pads_narrower_glyph_but_add_rejects_it() {
+        let base = art(&LETTER_A);
+        let mark = art(&["#......", ".......", ".......", ".......", ".......", ".......", ".......", "......#"]);
+        let overlaid = base.clone().overlay(mark.clone()).unwrap();
+        assert_eq!((overlaid.height, overlaid.width), (8, 8));
+        assert_eq!(overlaid.to_ascii_art().lines().next(), Some("#..##..."));
+        assert_eq!(overlaid.to_ascii_art().lines().last(), Some("......#."));
+        assert!(matches!(base.add(mark), 
+            Err(GlyphError::WrongDimensions{width: 8, expected_width: 7, ..})));
+    }
+
+    #[test]
+    fn bitmap_digest_ignores_grapheme() {
+        let a = art(&LETTER_A);
+        let greek_alpha = Glyph{grapheme: String::from("\u{391}"), ..a.clone()};
+        assert_eq!(a.bitmap_digest(), greek_alpha.bitmap_digest());
+        assert_ne!(a.bitmap_digest(), a.clone().invert().bitmap_digest());
+        // same bits, different shape.
+        let wide = Glyph{height: 4, width: 16, ..a.clone()};
+        assert_ne!(a.bitmap_digest(), wide.bitmap_digest());
+    }
+
+    #[test]
+    fn scale_nearest_round_trips_through_double_size() {
+        let glyph = art(&LETTER_A);
+        let doubled = glyph.clone().scale_nearest(16, 16);
+        assert_eq!(doubled.rows().nth(1).unwrap().count_ones(), 4);
+        assert_eq!(doubled.scale_nearest(8, 8).data, glyph.data);
+        // plain sampling round-trips too, since each block is a copy of one pixel.
+        assert_eq!(glyph.clone().scale(16, 16).scale(8, 8).data, glyph.data);
+    }
+
+    #[test]
+    fn scale_nearest_keeps_thin_strokes_when_shrinking() {
+        let stroke = art(&[".#..", ".#..", ".#..", ".#.."]);
+        assert_eq!(stroke.clone().scale_nearest(2, 2).to_ascii_art(), "#.\n#.");
+        assert_eq!(stroke.scale(2, 2).to_ascii_art(), "..\n..");
+    }
+
+    #[test]
+    fn scaling_an_empty_glyph_gives_a_blank_one() {
+        let empty = Glyph::blank(8, 0, String::from(" "), 8);
+        let scaled = empty.clone().scale_nearest(16, 4);
+        assert_eq!((scaled.height, scaled.width), (16, 4));
+        assert!(scaled.data.iter().all(|b| *b == 0));
+        let scaled = empty.scale(4, 2);
+        assert_eq!((scaled.height, scaled.width), (4, 2));
+        assert!(scaled.data.iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn squeeze_vertical_keeps_thin_horizontal_bar() {
+        // the bar is on an odd row, which plain row dropping would lose.
+        let bar = art(&[".....", ".....", ".....", "#####", ".....", "....."]);
+        assert_eq!(bar.squeeze_vertical().to_ascii_art(), ".....\n#####\n.....");
+        // an odd last row is kept.
+        let glyph = art(&["#..", "...", "..#"]).squeeze_vertical();
+        assert_eq!((glyph.height, glyph.to_ascii_art().as_str()), (2, "#..\n..#"));
+    }
+
+    #[test]
+    fn pixel_accessors_agree_with_rows() {
+        let mut glyph = art(&["#.........", "..........", "........#."]);
+        assert!(glyph.get_pixel(0, 0) && glyph.get_pixel(8, 2));
+        assert!(!glyph.get_pixel(9, 0) && !glyph.get_pixel(8, 1));
+        glyph.set_pixel(9, 1, true);
+        glyph.set_pixel(0, 0, false);
+        assert_eq!(glyph.to_ascii_art(), "..........\n.........#\n........#.");
+        assert_eq!(glyph.rows().map(|row| row.count_ones()).collect::<Vec<_>>(), [0, 1, 1]);
+        assert!(glyph.rows().all(|row| row.len() == 10));
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_pixel_panics_in_row_padding() {
+        // column 3 is a padding bit of the row's only byte, not part of the canvas.
+        art(&["###"]).get_pixel(3, 0);
+    }
+
+    #[test]
+    fn clear_padding_bits_cleans_dirty_rows() {
+        let clean = art(&["#.#", ".#.", "###", "#.."]);
+        let mut dirty = clean.clone();
+        for byte in dirty.data.iter_mut() {
+            *byte |= 0b0001_1111;
+        }
+        dirty.clear_padding_bits();
+        assert_eq!(dirty.data, clean.data);
+        // a row wider than one byte is cleaned only past its width.
+        let mut wide = art(&["#########"]);
+        wide.data[1] = 0xff;
+        wide.clear_padding_bits();
+        assert_eq!(wide.data, vec![0xff, 0x80]);
+    }
+
+    #[test]
+    fn flipping_twice_is_identity() {
+        let glyph = art(&["##...", "#.#..", "#..#.", "....#"]);
+        assert_eq!(glyph.clone().flip_horizontal().to_ascii_art(), ["...##", "..#.#", ".#..#", "#...."].join("\n"));
+        assert_eq!(glyph.clone().flip_horizontal().flip_horizontal().data, glyph.data);
+        assert_eq!(glyph.clone().flip_vertical().flip_vertical().data, glyph.data);
+        // the padding bits past the five real columns stay clear.
+        assert!(glyph.flip_horizontal().data.iter().all(|byte| byte & 0b0000_0111 == 0));
+    }
+
+    #[test]
+    fn shear_turns_vertical_stroke_diagonal() {
+        let stroke = art(&["#..", "#..", "#..", "#.."]);
+        assert_eq!(stroke.clone().shear(1.0).to_ascii_art(), 
+            ["...#..", "..#...", ".#....", "#....."].join("\n"));
+        // a left lean pivots on the same bottom row, from the other side of the widened canvas.
+        assert_eq!(stroke.shear(-0.5).to_ascii_art(), 
+            ["#....", ".#...", ".#...", "..#.."].join("\n"));
+    }
 }
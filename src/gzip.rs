@@ -0,0 +1,28 @@
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+/// The bytes every gzip file starts with.
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+/// The file extension that makes `convert` compress its output by default.
+pub const GZIP_EXTENSION: &str = "gz";
+
+/// Compresses `bytes` in the gzip format, as used for `.psf.gz` console fonts.
+pub fn compress(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(vec![], Compression::best());
+    encoder.write_all(bytes)?;
+    return encoder.finish();
+}
+
+/// Decompresses `bytes` if they start with the gzip magic bytes, and returns them unchanged
+/// otherwise.
+pub fn decompress_if_gzipped(bytes: Vec<u8>) -> std::io::Result<Vec<u8>> {
+    if !bytes.starts_with(&GZIP_MAGIC_BYTES) {
+        return Ok(bytes);
+    }
+    let mut decompressed = vec![];
+    GzDecoder::new(&bytes[..]).read_to_end(&mut decompressed)?;
+    return Ok(decompressed);
+}
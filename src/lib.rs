@@ -0,0 +1,31 @@
+#![allow(clippy::needless_return)]
+
+pub mod char_info;
+pub mod charset;
+pub mod errors;
+pub mod ttf_parser;
+pub mod psf2_writer;
+pub mod pff2_writer;
+pub mod unicode_table;
+pub mod unimap;
+pub mod glyph;
+pub mod gzip;
+pub mod report;
+pub mod profile;
+pub mod psf2_reader;
+pub mod concat;
+pub mod effects;
+pub mod psf2_text;
+pub mod svg_sheet;
+pub mod png_sheet;
+pub mod proportional;
+pub mod psf1;
+pub mod subset;
+pub mod c_header;
+mod conversion;
+
+pub use conversion::{convert, ConvertOptions};
+pub use glyph::Glyph;
+pub use psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header};
+pub use ttf_parser::TtfParser;
+pub use unicode_table::UnicodeTable;
@@ -1,20 +1,33 @@
-use clap::{Parser, Args, Subcommand};
+#![allow(clippy::needless_return)]
+
+use clap::{Parser, Args, Subcommand, ValueEnum};
+use itertools::Itertools;
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 
-mod errors;
-mod ttf_parser;
-mod psf2_writer;
-mod unicode_table;
-mod glyph;
-mod report;
+use otf2psf::{c_header, char_info, charset, concat, effects, errors, glyph, pff2_writer, profile, proportional, psf1, psf2_reader};
+use otf2psf::{gzip, png_sheet, psf2_writer, report, subset, svg_sheet, ttf_parser, unicode_table};
+
+/// The terminal width assumed by `report --sheet` when `$COLUMNS` is not set.
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
+/// The height `--auto` aims for when choosing among a font's embedded bitmap strikes.
+const AUTO_TARGET_HEIGHT: u32 = 16;
+/// The last codepoint `--auto` considers when building a Unicode table from the font's coverage.
+const AUTO_TABLE_LAST_CODEPOINT: u32 = 0xffff;
+/// The output path that `convert` takes to mean stdout.
+const STDOUT_PATH: &str = "-";
 
 #[derive(Debug, Parser)]
 #[command(version, about)]
 struct Cli {
     #[clap(subcommand)]
     command: Command,
+    /// Print more detail about each step to stderr. Repeat for more (`-vv`). `RUST_LOG` overrides
+    /// this, e.g. `RUST_LOG=otf2psf::ttf_parser=debug`.
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 }
 
 #[derive(Debug, Subcommand)]
@@ -22,7 +35,30 @@ enum Command {
     /// Report information about glyphs to stdout.
     Report(ReportOpts),
     /// Convert a TTF/OTF font to a PSF2 font.
-    Convert(ConvertOpts),
+    Convert(Box<ConvertOpts>),
+    /// Concatenate several PSF2 fonts into one, merging their Unicode tables.
+    #[command(alias = "merge")]
+    Concat(ConcatOpts),
+    /// Report a TTF/OTF font's vertical metrics, and the size of its capitals at common heights.
+    Measure(MeasureOpts),
+    /// Write a PSF2 font as editable text: its header, Unicode table, and glyphs as ASCII art.
+    ToText(TextOpts),
+    /// Read a font written by `to-text` back into a PSF2 font.
+    FromText(TextOpts),
+    /// Upgrade a PSF1 console font (8 pixels wide, 256 or 512 glyphs) to a PSF2 font.
+    FromPsf1(TextOpts),
+    /// Print the bytes of the glyph a PSF2 font uses for one character, in hex and as ASCII art.
+    ExtractGlyph(ExtractGlyphOpts),
+    /// Write a smaller PSF2 font with only the glyphs of a PSF2 font for the given characters.
+    Extract(ExtractOpts),
+    /// Scale every glyph of a PSF2 font to a new size, e.g. to double an 8x16 font to 16x32 for a
+    /// HiDPI console. Shrinking ORs together the pixels that merge, so thin strokes survive.
+    Resize(ResizeOpts),
+    /// Print the fields of a PSF2 font's header, without reading its glyphs.
+    Header(HeaderOpts),
+    /// Check the structure of a PSF2 font, printing each check as pass or fail. Exits with an
+    /// error if any check fails.
+    Verify(HeaderOpts),
 }
 
 #[derive(Debug, Args)]
@@ -33,6 +69,10 @@ struct ReportOpts {
     /// The target font height, in pixels.
     #[clap(default_value_t = 16)]
     height: u32,
+    /// Report on each character at several heights, as a comma-separated list (e.g. `12,14,16`),
+    /// in a table with a column per height.
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["height", "strikes", "sheet", "diff_vs_strike"])]
+    heights: Vec<u32>,
     /// Report on all the characters that would be used to generate a PSF2 font from 
     /// a Unicode mapping table.
     #[clap(long, group="report-source")]
@@ -43,107 +83,1106 @@ struct ReportOpts {
     /// Report on the Unicode block that contains a given character.
     #[clap(long, group="report-source")]
     block_containing: Option<char>,
+    /// Report the font's embedded bitmap strikes: their sizes and image formats. Converting at one
+    /// of these sizes uses the font's own bitmaps instead of rasterizing outlines.
+    #[clap(long, visible_alias = "list-strikes", group="report-source")]
+    strikes: bool,
+    /// Report every codepoint the font maps: how many there are, how many are embedded bitmaps
+    /// or outlines, and how much of each Unicode block they cover.
+    #[clap(long, group="report-source")]
+    coverage: bool,
+    /// Instead of describing each glyph, draw every glyph as ASCII art in a grid, wrapped to the
+    /// terminal width (from `$COLUMNS`, or 80 columns).
+    #[arg(long, action)]
+    sheet: bool,
+    /// With `--sheet`, draw each rasterized glyph's outline coverage before it is flattened to one
+    /// bit, shaded from ` ` (uncovered) to `#` (fully covered), to the right of the glyph.
+    #[arg(long, action, requires = "sheet")]
+    gray_preview: bool,
+    /// The fraction of a pixel, in (0, 1], that an outline must cover for the pixel to be set, as
+    /// with `convert --threshold`.
+    #[arg(long, value_parser = parse_threshold, default_value_t = ttf_parser::COVERAGE_THRESHOLD)]
+    threshold: f32,
+    /// Instead of describing each glyph, compare each glyph's embedded bitmap with its rasterized
+    /// outline, and list the N characters whose two renderings differ in the most pixels.
+    #[arg(long, value_name = "N", conflicts_with = "sheet")]
+    diff_vs_strike: Option<usize>,
 }
 
 #[derive(Debug, Args)]
 struct ConvertOpts {
     /// A path to a TTF or OTF font file.
     ttf_file: PathBuf,
-    /// A path to an output file, where a generated PSF2 font will be stored.
+    /// A path to an output file, where the generated font will be stored, or `-` to write the font
+    /// to stdout (e.g. to pipe it into `gzip`).
     output_file: PathBuf,
-    /// The target font height, in pixels.
+    /// The target font height, in pixels, as measured by `--size-metric`.
     #[clap(default_value_t = 16)]
     height: u32,
+    /// Instead of using `height`, pick the largest height up to this many pixels at which no 
+    /// glyph's outline is clipped by its canvas. The fit is measured on the input font's
+    /// advance-width canvas at the baseline, so it cannot be combined with options that move or
+    /// resize that canvas, or with fallback fonts.
+    #[arg(long, conflicts_with_all = ["height", "baseline_offset", "cell_width", "supersample", "fallback_font"])]
+    fit_height: Option<u32>,
+    /// What `height` measures: `cell`, the whole glyph cell from the font's ascent to its
+    /// descent; `em`, the em square the font is designed on; or `cap`, the height of capital
+    /// letters. With `em` or `cap`, the cell is as tall as it needs to be.
+    #[arg(long, value_enum, default_value_t = ttf_parser::SizeMetric::Cell, conflicts_with_all = ["fit_height", "auto"])]
+    size_metric: ttf_parser::SizeMetric,
     /// A path to a file specifying a Unicode mapping table.
     #[clap(short, long)]
     unicode_table_file: Option<PathBuf>,
+    /// A path to a Unicode mapping table in the console-tools `.uni` format, as written by
+    /// `psfgettable`, to use instead of `unicode_table_file`.
+    #[arg(long, conflicts_with = "unicode_table_file")]
+    unimap_file: Option<PathBuf>,
+    /// A font to take characters from when the input font does not define them. May be given
+    /// more than once; fallback fonts are tried in order. Each must render U+0020 SPACE in a cell
+    /// of the same size as the input font, unless `--pad` or `--pad-to-mode-size` is given.
+    #[arg(long)]
+    fallback_font: Vec<PathBuf>,
+    /// Render characters that neither the input font nor any fallback font defines as U+FFFD
+    /// REPLACEMENT CHARACTER, or as a box if no font defines U+FFFD either.
+    #[arg(long, action)]
+    fallback_to_fffd: bool,
+    /// Render characters that neither the input font nor any fallback font defines as blank
+    /// glyphs the size of the font's U+0020 SPACE cell, rather than as the font's `.notdef` box.
+    /// Works with or without a Unicode table. Without one, this keeps the bytes of undefined
+    /// codepoints from depending on the font; `--deterministic-blank` is accepted as an alias.
+    #[arg(long, action, alias = "deterministic-blank", conflicts_with = "fallback_to_fffd")]
+    blank_missing: bool,
+    /// With a Unicode table, render entries that neither the input font nor any fallback font
+    /// defines as the font's `.notdef` glyph, instead of exiting with an error that lists them.
+    /// Implied by `--blank-missing` and `--fallback-to-fffd`.
+    #[arg(long, action)]
+    allow_missing: bool,
+    /// How to handle embedded bitmaps whose strike height differs from the requested height. By
+    /// default, such bitmaps are used at their own height.
+    #[arg(long, value_enum)]
+    strike_height: Option<ttf_parser::StrikeHeightPolicy>,
+    /// Merge Unicode table entries that share a grapheme into a single glyph, instead of exiting
+    /// with an error.
+    #[arg(long, action)]
+    merge_equiv: bool,
+    /// Store glyphs that look identical only once, listing all of their graphemes in the Unicode
+    /// table entry of the first. Without a Unicode table, one is added.
+    #[arg(long, action, conflicts_with = "by_glyph_id")]
+    dedup: bool,
+    /// Pick sensible settings for this font: the embedded bitmap strike closest to 16 px if there
+    /// are any (otherwise 16 px), a Unicode table of ASCII plus every other character the font
+    /// defines (up to `--max-glyphs`), and `--pad`. The chosen settings are printed to stderr.
+    #[arg(long, action, conflicts_with_all = ["height", "fit_height", "unicode_table_file", "unimap_file", 
+        "by_glyph_id", "match_coverage", "glyph_count"])]
+    auto: bool,
+    /// Which grapheme of each Unicode table entry to render as the entry's glyph: `first` (the one
+    /// with the fewest codepoints), `longest` (the one with the most, e.g. a ligature), or
+    /// `specific:<n>` (the `n`th, counting from 1 in order of codepoint count, or the first if the
+    /// entry has fewer). All of the entry's graphemes are mapped to that glyph either way.
+    #[arg(long, value_parser = parse_render_grapheme, default_value_t = psf2_writer::RenderGrapheme::First)]
+    render_grapheme: psf2_writer::RenderGrapheme,
+    /// Render the font's glyphs in glyph id order, ignoring its character map. Useful for symbol
+    /// fonts without a Unicode cmap; glyph `i` of the output is glyph id `i` of the input.
+    #[arg(long, action, conflicts_with_all = ["unicode_table_file", "unimap_file", "fit_height", "match_coverage"])]
+    by_glyph_id: bool,
+    /// Only convert characters that are defined in both the input font and this reference font,
+    /// e.g. to give a pair of fonts the same coverage. Without a Unicode table, the candidate
+    /// characters are the first `glyph_count` codepoints, and a Unicode table is generated.
+    #[arg(long)]
+    match_coverage: Option<PathBuf>,
     /// The number of glyphs to include in the finished font. 
     // If a Unicode table is also specified, at most `glyph_count` glyphs will be included from the table. 
     // If a Unicode table is not specified, `glyph_count` glyphs will be generated, corresponding to 
     // Unicode codepoints `0` through `(glyph_count - 1)`. The default is the length of the Unicode table, 
-    // if included, or 256 if no Unicode table is included.
+    // if included, or the size of `--charset-preset` if no Unicode table is included.
     #[arg(short, long)]
     glyph_count: Option<u32>,
+    /// Without a Unicode table, which characters to convert: `ascii` (128 glyphs), `latin1` (256,
+    /// the default), `vga` (the 256 characters of code page 437, with a Unicode table), or `full`
+    /// (512). `--glyph-count` overrides the preset's glyph count.
+    #[arg(long, value_enum, conflicts_with_all = ["unicode_table_file", "unimap_file", "auto", "by_glyph_id"])]
+    charset_preset: Option<charset::CharsetPreset>,
+    /// Where the width of each rasterized glyph's canvas comes from. `ink` and `advance` widths
+    /// vary between glyphs in proportional fonts, so they usually need `--pad`; `em` gives every
+    /// rasterized glyph the same width.
+    #[arg(long, value_enum, default_value_t = ttf_parser::CellWidth::Advance)]
+    cell_width: ttf_parser::CellWidth,
+    /// The fraction of a pixel, in (0, 1], that an outline must cover for the pixel to be set.
+    /// Lower values keep more of thin glyphs; higher values thin out heavy ones. Also the cutoff
+    /// for flattening grayscale embedded bitmaps.
+    #[arg(long, value_parser = parse_threshold, default_value_t = ttf_parser::COVERAGE_THRESHOLD)]
+    threshold: f32,
+    /// How rasterized outlines with partly covered pixels are flattened to one bit. `dither`
+    /// often keeps more detail at small sizes. Only affects rasterized outlines, not embedded
+    /// bitmaps.
+    #[arg(long, value_enum, default_value_t = ttf_parser::RasterMode::Threshold)]
+    raster_mode: ttf_parser::RasterMode,
+    /// Draw outlines this many times larger (1 to 4), then average each block of pixels into one
+    /// before flattening it at `--threshold`. Gives cleaner edges than drawing at the small size
+    /// directly. Only affects rasterized outlines, not embedded bitmaps.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=4), default_value_t = 1)]
+    supersample: u32,
+    /// Move every rasterized outline this many pixels down its cell from where the font's ascent
+    /// puts it, or up if negative, e.g. to keep descenders from being clipped. Does not affect
+    /// embedded bitmaps.
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0)]
+    baseline_offset: i32,
+    /// Reorder the glyphs and Unicode table entries together by codepoint, for consumers that
+    /// look characters up with a binary search. Glyph `i` still matches table entry `i`. Overrides
+    /// the order of the Unicode table file, including any pinned glyph indices.
+    #[arg(long, action)]
+    sort_table: bool,
+    /// Halve the height of every glyph by combining each pair of rows, for strikes or outlines
+    /// that are too tall for the target cell. Thin horizontal strokes are kept, at some cost in
+    /// quality.
+    #[arg(long, action)]
+    squeeze_v: bool,
+    /// Remove the blank columns on either side of each glyph, after effects are applied, then pad
+    /// every glyph back to the width of the widest one. Tightens fonts whose glyphs have wide side
+    /// bearings. Implies `--pad`.
+    #[arg(long, action, conflicts_with = "width_from_space")]
+    trim: bool,
+    /// Pad or crop every glyph to the width of the font's space character, which is the true cell
+    /// width of a monospace font. Ink past the right edge of the cell is cropped.
+    #[arg(long, action)]
+    width_from_space: bool,
+    /// Mirror each glyph left to right, e.g. to build mirrored box-drawing characters.
+    #[arg(long, action)]
+    flip_h: bool,
+    /// Mirror each glyph top to bottom.
+    #[arg(long, action)]
+    flip_v: bool,
+    /// Thicken each glyph's strokes by one pixel to the right. Glyphs keep their size, so ink in
+    /// the rightmost column cannot spread further and `--pad` is not needed.
+    #[arg(long, action)]
+    bold: bool,
+    /// Slant each glyph to the right by shearing it, pivoting on the bottom row of the cell. The
+    /// cell is widened to fit the slant. Implies `--pad`.
+    #[arg(long, action)]
+    italic: bool,
+    /// How far `--italic` shifts each row, in pixels per row above the bottom of the cell. A
+    /// negative slope leans glyphs to the left.
+    #[arg(long, requires = "italic", allow_negative_numbers = true, default_value_t = effects::DEFAULT_ITALIC_SLOPE)]
+    italic_slope: f32,
+    /// Replace each glyph's ink with a one-pixel outline around it.
+    #[arg(long, action)]
+    outline: bool,
+    /// Draw a line across each glyph's width, along its bottom row unless `--underline-row` is
+    /// given, e.g. to build the underlined variant of a terminal font.
+    #[arg(long, action)]
+    underline: bool,
+    /// The row to draw `--underline` at, counted from the top of the glyph.
+    #[arg(long, requires = "underline")]
+    underline_row: Option<u32>,
+    /// Draw a line across each glyph's width, along its middle row unless `--strikethrough-row`
+    /// is given.
+    #[arg(long, action)]
+    strikethrough: bool,
+    /// The row to draw `--strikethrough` at, counted from the top of the glyph.
+    #[arg(long, requires = "strikethrough")]
+    strikethrough_row: Option<u32>,
+    /// Swap each glyph's set and blank pixels.
+    #[arg(long, action)]
+    invert: bool,
+    /// The order to apply effects in, as a comma-separated list (e.g. `outline,bold`). Every listed
+    /// effect must be enabled; enabled effects that are not listed are applied afterwards, in the
+    /// default order: flip-h, flip-v, bold, italic, outline, underline, strikethrough, invert.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    effects_order: Vec<effects::Effect>,
     /// Pad all glyphs to the canvas size of the largest glyph. 
     // Helpful for dealing with fonts where some special characters have unusually small canvases. 
     // If this flag is not set, this tool will require all glyphs to be the same size, and will exit 
     // with an error otherwise.
     #[arg(long, action)]
     pad: bool,
+    /// Where `--pad` places each smaller glyph in the padded cell. Centering helps symbol fonts,
+    /// whose narrow glyphs look wrong left-aligned.
+    #[arg(long, value_enum, default_value_t = psf2_writer::PadAnchor::LeftTop)]
+    pad_anchor: psf2_writer::PadAnchor,
+    /// Pad or crop only the glyphs whose size differs from the most common glyph size, rather than
+    /// growing every glyph to fit the largest one. Adjusted glyphs are listed on stderr.
+    #[arg(long, action, conflicts_with = "pad")]
+    pad_to_mode_size: bool,
+    /// Grow every glyph's cell by a blank margin, given as `<right>x<bottom>` in pixels (e.g.
+    /// `1x2`). Unlike `--pad`, the margin is added unconditionally to every glyph.
+    #[arg(long, value_parser = parse_glyph_gap)]
+    glyph_gap: Option<(u32, u32)>,
+    /// The largest number of glyphs the target console accepts. The Linux console accepts at
+    /// most 512 glyphs; some other setups allow more.
+    #[arg(long, default_value_t = 512)]
+    max_glyphs: u32,
+    /// Exit with an error, rather than a warning, if the font has more than `max_glyphs` glyphs.
+    #[arg(long, action)]
+    strict: bool,
+    /// Exit with an error, rather than a warning, if any rasterized glyph's outline is clipped by
+    /// its cell.
+    #[arg(long, action)]
+    strict_clipping: bool,
+    /// Also write the font's Unicode mapping to this path, in the console-tools `.uni` format used
+    /// by `psfaddtable` and `setfont -u`.
+    #[arg(long)]
+    write_unimap: Option<PathBuf>,
+    /// Exit with an error, rather than padding the smaller glyphs, if the characters of a
+    /// multi-character grapheme (e.g. a base letter and a combining mark) render at different sizes.
+    #[arg(long, action)]
+    strict_compose: bool,
+    /// Also draw every glyph to this path as an SVG contact sheet, labeled by codepoint.
+    #[arg(long)]
+    svg: Option<PathBuf>,
+    /// The number of glyphs in each row of a `--format png-sheet` image.
+    #[arg(long, default_value_t = 16)]
+    sheet_columns: usize,
+    /// Print every glyph's bitmap to stderr as run-length-encoded rows, for debugging.
+    #[arg(long, action)]
+    dump_rle: bool,
+    /// The pixels-per-em size to declare in exported font metadata (e.g. the PFF2 point size).
+    /// Defaults to the requested height, even if embedded bitmap strikes of another size are used.
+    #[arg(long)]
+    ppem_override: Option<u32>,
+    /// Zero-pad the output file to exactly this many bytes, e.g. to fill a fixed-size ROM region.
+    /// Exits with an error if the font is already larger. Unrelated to `--pad`.
+    #[arg(long)]
+    pad_file: Option<usize>,
+    /// Accepted for build scripts that ask for reproducible output. Has no effect: every output
+    /// already depends only on the input font and the options given.
+    #[arg(long, action)]
+    repeatable: bool,
+    /// Print the wall-clock time spent in each phase of the conversion to stderr.
+    #[arg(long, action)]
+    profile: bool,
+    /// Print this many of the slowest glyphs to render, and how long each took, to stderr.
+    #[arg(long)]
+    report_timing_per_glyph: Option<usize>,
+    /// The format of the output font file.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Psf2)]
+    format: OutputFormat,
+    /// Compress the output file with gzip. This is the default if the output file name ends in
+    /// `.gz`.
+    #[arg(long, action, overrides_with = "no_compress")]
+    compress: bool,
+    /// Do not compress the output file, even if its name ends in `.gz`.
+    #[arg(long, action, overrides_with = "compress")]
+    no_compress: bool,
+    /// Run the whole conversion, but write no files. Prints the header of a PSF2 output and the
+    /// size of each file that would have been written instead.
+    #[arg(long, action)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct ConcatOpts {
+    /// A path to an output file, where the concatenated font will be stored.
+    output_file: PathBuf,
+    /// Paths to PSF2 fonts, whose glyphs are appended in order.
+    #[arg(required = true)]
+    psf2_files: Vec<PathBuf>,
+    /// Pad all glyphs to the canvas size of the largest glyph, instead of requiring every font to
+    /// have the same dimensions.
+    #[arg(long, action)]
+    pad: bool,
+    /// What to do when glyphs from different fonts represent the same character.
+    #[arg(long, value_enum, default_value_t = concat::CollisionPolicy::Error)]
+    on_collision: concat::CollisionPolicy,
+}
+
+#[derive(Debug, Args)]
+struct MeasureOpts {
+    /// A path to a TTF or OTF font file.
+    ttf_file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct TextOpts {
+    /// A path to the font to read.
+    input_file: PathBuf,
+    /// A path to an output file, where the converted font will be stored.
+    output_file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ExtractGlyphOpts {
+    /// A path to a PSF2 font, or a font written with `--format proportional-bin`.
+    psf2_file: PathBuf,
+    /// The character to look up, as a codepoint such as `U+0041`.
+    codepoint: String,
+}
+
+#[derive(Debug, Args)]
+struct ExtractOpts {
+    /// A path to a PSF2 font.
+    psf2_file: PathBuf,
+    /// A path to an output file, where the smaller font will be stored.
+    output_file: PathBuf,
+    /// The characters to keep, as a comma-separated list of codepoints and ranges (e.g.
+    /// `U+0020..U+007E,U+00E9`). Glyphs are written in this order.
+    #[arg(required = true, value_delimiter = ',')]
+    codepoints: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+struct ResizeOpts {
+    /// A path to a PSF2 font.
+    psf2_file: PathBuf,
+    /// A path to an output file, where the resized font will be stored.
+    output_file: PathBuf,
+    /// The new glyph height, in pixels.
+    #[arg(value_parser = clap::value_parser!(u32).range(1..))]
+    height: u32,
+    /// The new glyph width, in pixels. By default, the width is scaled by the same factor as the
+    /// height.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+}
+
+#[derive(Debug, Args)]
+struct HeaderOpts {
+    /// A path to a PSF2 font.
+    psf2_file: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// A PSF2 Linux console font.
+    Psf2,
+    /// A GRUB PFF2 bootloader font.
+    Pff2,
+    /// A PSF1 Linux console font, for older tools. Glyphs must be 8 pixels wide.
+    Psf1,
+    /// A C header holding the bytes of a PSF2 font as a `const uint8_t` array named after the
+    /// output file, with its dimensions as `#define`s, for embedding in firmware.
+    CHeader,
+    /// A PNG image of every glyph in a grid, for previewing the font. Not a font file.
+    PngSheet,
+    /// A simple binary format for custom renderers, where each glyph keeps its rendered width.
+    /// `--svg` and `--dump-rle` are ignored for this format.
+    ProportionalBin,
+}
+
+/// Parses a `--glyph-gap` value of the form `<right>x<bottom>`.
+fn parse_glyph_gap(value: &str) -> Result<(u32, u32), String> {
+    let (right, bottom) = value.split_once('x')
+        .ok_or(format!("expected a gap of the form <right>x<bottom>, e.g. 1x2, but got {:?}", value))?;
+    let right = right.parse::<u32>().map_err(|e| format!("invalid right gap {:?}: {}", right, e))?;
+    let bottom = bottom.parse::<u32>().map_err(|e| format!("invalid bottom gap {:?}: {}", bottom, e))?;
+    return Ok((right, bottom));
+}
+
+/// Parses a `--render-grapheme` value: `first`, `longest`, or `specific:<n>` with `n` at least 1.
+fn parse_render_grapheme(value: &str) -> Result<psf2_writer::RenderGrapheme, String> {
+    return match value.split_once(':') {
+        None if value == "first" => Ok(psf2_writer::RenderGrapheme::First),
+        None if value == "longest" => Ok(psf2_writer::RenderGrapheme::Longest),
+        Some(("specific", n)) => match n.parse::<usize>() {
+            Ok(n) if n >= 1 => Ok(psf2_writer::RenderGrapheme::Specific(n)),
+            _ => Err(format!("expected a grapheme number of at least 1 after specific:, but got {:?}", n)),
+        },
+        _ => Err(format!("expected first, longest, or specific:<n>, but got {:?}", value)),
+    };
+}
+
+/// Parses a `--threshold` value, which must be in (0, 1].
+fn parse_threshold(value: &str) -> Result<f32, String> {
+    let threshold = value.parse::<f32>().map_err(|e| format!("invalid threshold {:?}: {}", value, e))?;
+    if !(threshold > 0.0 && threshold <= 1.0) {
+        return Err(format!("the threshold must be greater than 0 and at most 1, but got {}", threshold));
+    }
+    return Ok(threshold);
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli_options = Cli::parse();
+    colog::basic_builder()
+        .filter_level(match cli_options.verbose {
+            0 => log::LevelFilter::Info,
+            1 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        })
+        .parse_default_env()
+        .init();
     return match cli_options.command {
         Command::Report(options) => {
             report(options)
         }
         Command::Convert(options) => {
-            convert(options)
+            convert(*options)
         }
+        Command::Concat(options) => {
+            concat(options)
+        }
+        Command::Measure(options) => {
+            measure(options)
+        }
+        Command::ToText(options) => {
+            to_text(options)
+        }
+        Command::FromText(options) => {
+            from_text(options)
+        }
+        Command::FromPsf1(options) => {
+            from_psf1(options)
+        }
+        Command::ExtractGlyph(options) => {
+            extract_glyph(options)
+        }
+        Command::Extract(options) => {
+            extract(options)
+        }
+        Command::Resize(options) => {
+            resize(options)
+        }
+        Command::Header(options) => {
+            header(options)
+        }
+        Command::Verify(options) => {
+            verify(options)
+        }
+    }
+}
+
+fn concat(concat_opts: ConcatOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let mut fonts = vec![];
+    for psf2_file in concat_opts.psf2_files.iter() {
+        let font = psf2_writer::Psf2Font::from_bytes(&read_font_file(psf2_file)?)
+            .map_err(|e| format!("While reading {}: {}", psf2_file.display(), e))?;
+        fonts.push(font);
     }
+    let font = concat::concat_fonts(fonts, concat_opts.pad, concat_opts.on_collision)?;
+    log::info!("Glyph count: {}", font.header.glyph_count);
+    fs::write(&concat_opts.output_file, font.write())?;
+    log::info!("Wrote PSF2 font file.");
+    Ok(())
+}
+
+fn to_text(text_opts: TextOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let font = psf2_writer::Psf2Font::from_bytes(&read_font_file(&text_opts.input_file)?)?;
+    fs::write(&text_opts.output_file, font.to_debug_text())?;
+    Ok(())
+}
+
+fn from_text(text_opts: TextOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let font = psf2_writer::Psf2Font::from_debug_text(&fs::read_to_string(&text_opts.input_file)?)?;
+    fs::write(&text_opts.output_file, font.write())?;
+    log::info!("Wrote PSF2 font file.");
+    Ok(())
+}
+
+fn from_psf1(text_opts: TextOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let psf1_font = psf1::Psf1Font::from_bytes(&read_font_file(&text_opts.input_file)?)
+        .map_err(|e| format!("While reading {}: {}", text_opts.input_file.display(), e))?;
+    let font = psf2_writer::Psf2Font::from_psf1(psf1_font)?;
+    fs::write(&text_opts.output_file, font.write())?;
+    log::info!("Wrote PSF2 font file.");
+    Ok(())
+}
+
+fn extract_glyph(extract_glyph_opts: ExtractGlyphOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let psf2_file = &extract_glyph_opts.psf2_file;
+    let hex_digits = extract_glyph_opts.codepoint.trim_start_matches(['U', 'u']).trim_start_matches('+');
+    let character = unicode_table::parse_codepoint(hex_digits)?;
+    let bytes = read_font_file(psf2_file)?;
+    let (glyphs, unicode_table) = if bytes.starts_with(&proportional::PROPORTIONAL_MAGIC_BYTES) {
+        let font = proportional::ProportionalFont::read(&bytes)?;
+        (font.glyphs, font.unicode_table)
+    } else {
+        let font = psf2_writer::Psf2Font::from_bytes(&bytes)?;
+        (font.glyphs.glyphs().to_vec(), font.unicode_table)
+    };
+    let index = match &unicode_table {
+        Some(uc) => uc.data.iter().position(|group| group.iter().any(|g| g.chars().eq([character]))),
+        None => Some(u32::from(character) as usize).filter(|i| *i < glyphs.len()),
+    }.ok_or(format!("{} is not mapped to any glyph in {}.", char_info::char_name(character), psf2_file.display()))?;
+
+    println!("Glyph {} for {}:", index, char_info::char_name(character));
+    report::print_glyph_bytes(&glyphs[index]);
+    Ok(())
+}
+
+fn extract(extract_opts: ExtractOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let psf2_file = &extract_opts.psf2_file;
+    let characters: Vec<char> = extract_opts.codepoints.iter()
+        .map(|c| unicode_table::parse_codepoint_range(c))
+        .flatten_ok()
+        .collect::<Result<Vec<char>, _>>()?;
+    let font = psf2_writer::Psf2Font::from_bytes(&read_font_file(psf2_file)?)
+        .map_err(|e| format!("While reading {}: {}", psf2_file.display(), e))?;
+    let font = subset::subset_font(font, &characters)
+        .map_err(|e| format!("While reading {}: {}", psf2_file.display(), e))?;
+    log::info!("Glyph count: {}", font.header.glyph_count);
+    fs::write(&extract_opts.output_file, font.write())?;
+    log::info!("Wrote PSF2 font file.");
+    Ok(())
+}
+
+fn resize(resize_opts: ResizeOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let psf2_file = &resize_opts.psf2_file;
+    let font = psf2_writer::Psf2Font::from_bytes(&read_font_file(psf2_file)?)
+        .map_err(|e| format!("While reading {}: {}", psf2_file.display(), e))?;
+    let height = resize_opts.height;
+    let width = resize_opts.width.unwrap_or_else(|| 
+        std::cmp::max(1, (font.header.glyph_width as f64 * height as f64 / font.header.glyph_height as f64).round() as u32));
+    let glyphs = font.glyphs.glyphs().iter()
+        .map(|g| g.clone().scale_nearest(height, width))
+        .collect();
+    let glyphs = psf2_writer::Psf2GlyphSet::from_glyphs(glyphs, false)?;
+    let header = psf2_writer::Psf2Header{
+        glyph_size: glyphs.length,
+        glyph_height: glyphs.height,
+        glyph_width: glyphs.width,
+        ..font.header
+    };
+    log::info!("Glyph size: {} x {} px", height, width);
+    fs::write(&resize_opts.output_file, psf2_writer::Psf2Font{header, glyphs, unicode_table: font.unicode_table}.write())?;
+    log::info!("Wrote PSF2 font file.");
+    Ok(())
+}
+
+fn header(header_opts: HeaderOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let psf2_file = &header_opts.psf2_file;
+    let header = psf2_reader::read_psf2_header(&read_font_file(psf2_file)?)
+        .map_err(|e| format!("While reading {}: {}", psf2_file.display(), e))?;
+    report::print_psf2_header(&header);
+    Ok(())
+}
+
+fn verify(verify_opts: HeaderOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let psf2_file = &verify_opts.psf2_file;
+    let checks = psf2_reader::verify_psf2(&read_font_file(psf2_file)?)
+        .map_err(|e| format!("While reading {}: {}", psf2_file.display(), e))?;
+    report::print_psf2_checks(&checks);
+    let failed_count = checks.iter().filter(|c| !c.passed).count();
+    if failed_count > 0 {
+        return Err(format!("{} of {} checks failed for {}.", failed_count, checks.len(), psf2_file.display()).into());
+    }
+    Ok(())
+}
+
+fn measure(measure_opts: MeasureOpts) -> Result<(), Box<dyn std::error::Error>> {
+    let metrics = ttf_parser::TtfParser::metrics(&measure_opts.ttf_file)?;
+    report::print_metrics(&metrics);
+    Ok(())
 }
 
 fn report(report_opts: ReportOpts) -> Result<(), Box <dyn std::error::Error>> {
     let ttf_file = &report_opts.ttf_file;
     let height = report_opts.height;
-    let ttf_parser = ttf_parser::TtfParser::from_font_path(ttf_file, height)?;
+    let mut ttf_parser = ttf_parser::TtfParser::from_font_path(ttf_file, height)?;
+    ttf_parser.set_coverage_threshold(report_opts.threshold);
+
+    if report_opts.strikes {
+        report::report_strikes(&ttf_parser);
+        return Ok(());
+    }
+    if report_opts.coverage {
+        report::report_coverage(&ttf_parser);
+        return Ok(());
+    }
 
-    if let Some(uc) = &report_opts.unicode_table_file {
-        crate::report::report_unicode_table(ttf_parser, uc)?;
+    let characters: Vec<char> = if let Some(uc) = &report_opts.unicode_table_file {
+        let unicode_table = unicode_table::UnicodeTable::from_file(uc, None, true)?;
+        report::print_unicode_table_meta(&unicode_table.meta);
+        report::unicode_table_chars(unicode_table)
     } else if let Some(block_char) = report_opts.block_containing {
-        crate::report::report_unicode_block(ttf_parser, unicode_blocks::find_unicode_block(block_char)
-            .ok_or("No Unicode block found matching character")?);
+        report::unicode_block_chars(unicode_blocks::find_unicode_block(block_char)
+            .ok_or(format!("No Unicode block found containing {}", char_info::char_name(block_char)))?)
     } else if let Some(single_char) = report_opts.single_character {
-        println!("{}", ttf_parser.report_char(single_char));
+        vec![single_char]
+    } else {
+        vec![]
+    };
+
+    if !report_opts.heights.is_empty() {
+        let ttf_parsers = report_opts.heights.iter()
+            .map(|h| Ok((*h, ttf_parser::TtfParser::from_font_path(ttf_file, *h)?)))
+            .collect::<Result<Vec<_>, errors::TtfParserError>>()?;
+        report::report_char_table(&ttf_parsers, &characters);
+    } else if let Some(count) = report_opts.diff_vs_strike {
+        report::report_strike_divergence(&ttf_parser, &characters, count)?;
+    } else if report_opts.sheet {
+        let terminal_width = std::env::var("COLUMNS").ok()
+            .and_then(|c| c.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_TERMINAL_WIDTH);
+        report::print_sheet(&ttf_parser, &characters, terminal_width, report_opts.gray_preview)?;
+    } else {
+        report::report_char_vec(&ttf_parser, &characters);
     }
     Ok(())
 }
 
 fn convert(convert_opts: ConvertOpts) -> Result <(), Box<dyn std::error::Error>> { 
     let ttf_file = &convert_opts.ttf_file;
-    let height = convert_opts.height;
     let unicode_table_file = &convert_opts.unicode_table_file;
     let output_file = &convert_opts.output_file;
     let cli_glyph_count = convert_opts.glyph_count;
-    let pad = convert_opts.pad;
-
+    let charset_preset = convert_opts.charset_preset.unwrap_or(charset::DEFAULT_CHARSET_PRESET);
+    let pad = convert_opts.pad || convert_opts.auto || convert_opts.trim || convert_opts.italic;
+    if convert_opts.repeatable {
+        log::debug!("--repeatable has no effect, since every output is already reproducible.");
+    }
+    let format = convert_opts.format;
+    let max_glyphs = convert_opts.max_glyphs;
+    let strict = convert_opts.strict;
+    let allow_missing = convert_opts.allow_missing || convert_opts.blank_missing || convert_opts.fallback_to_fffd;
+    let dry_run = convert_opts.dry_run;
+    let compress = convert_opts.compress || (!convert_opts.no_compress 
+        && output_file.extension().is_some_and(|e| e == gzip::GZIP_EXTENSION));
+    let mut profile = profile::Profile::new();
+    let mut glyph_timings = profile::GlyphTimings::new(convert_opts.report_timing_per_glyph.unwrap_or(0));
 
-    let ttf_parser = ttf_parser::TtfParser::from_font_path(
-        ttf_file,
-        height,
-    )?;
+    let enabled_effects: Vec<effects::Effect> = [
+        (convert_opts.flip_h, effects::Effect::FlipH),
+        (convert_opts.flip_v, effects::Effect::FlipV),
+        (convert_opts.bold, effects::Effect::Bold),
+        (convert_opts.italic, effects::Effect::Italic),
+        (convert_opts.outline, effects::Effect::Outline),
+        (convert_opts.underline, effects::Effect::Underline),
+        (convert_opts.strikethrough, effects::Effect::Strikethrough),
+        (convert_opts.invert, effects::Effect::Invert),
+    ].into_iter().filter(|(enabled, _)| *enabled).map(|(_, effect)| effect).collect();
+    let effect_settings = effects::EffectSettings{
+        italic_slope: convert_opts.italic_slope,
+        underline_row: convert_opts.underline_row, 
+        strikethrough_row: convert_opts.strikethrough_row,
+    };
+    let effects = effects::effects_pipeline(&enabled_effects, &convert_opts.effects_order, effect_settings)?;
 
-    let (unicode_table, glyph_count, glyphs) = match unicode_table_file {
-        Some(p) => {
-            let unicode_table = unicode_table::UnicodeTable::from_file(p, cli_glyph_count)?;
-            let uc_table_glyph_count = unicode_table.data.len() as u32;
-            let glyphs = psf2_writer::Psf2GlyphSet::new_with_unicode_table(ttf_parser, &unicode_table, pad)?;
-            (Some(unicode_table), uc_table_glyph_count, glyphs)
+    // with --match-coverage, --glyph-count applies after entries are filtered out.
+    let table_glyph_count = if convert_opts.match_coverage.is_some() {None} else {cli_glyph_count};
+    let mut unicode_table = match (unicode_table_file, &convert_opts.unimap_file) {
+        (Some(p), _) => Some(unicode_table::UnicodeTable::from_file(p, table_glyph_count, convert_opts.merge_equiv)?),
+        (None, Some(p)) => Some(unicode_table::UnicodeTable::from_unimap_file(p, table_glyph_count, convert_opts.merge_equiv)?),
+        (None, None) => charset_preset.unicode_table().map(|mut table| {
+            table.data.truncate(table_glyph_count.unwrap_or(u32::MAX) as usize);
+            table
+        }),
+    };
+    let mut auto_height: Option<u32> = None;
+    if convert_opts.auto {
+        let probe = ttf_parser::TtfParser::from_font_path(ttf_file, AUTO_TARGET_HEIGHT)?;
+        let strike_height = probe.strike_formats().iter()
+            .map(|(ppem, _)| *ppem as u32)
+            .min_by_key(|ppem| ppem.abs_diff(AUTO_TARGET_HEIGHT));
+        // ASCII keeps its own indices, which the console relies on (see the README's caveats).
+        let table = unicode_table::UnicodeTable::from_chars((0..=AUTO_TABLE_LAST_CODEPOINT)
+            .filter_map(char::from_u32)
+            .filter(|c| c.is_ascii() || probe.is_defined(*c))
+            .take(max_glyphs as usize));
+        log::info!("--auto chose: height {} px ({}), a Unicode table of {} characters (ASCII and the font's coverage), \
+            and --pad. Use --write-unimap to save the table.", 
+            strike_height.unwrap_or(AUTO_TARGET_HEIGHT), 
+            if strike_height.is_some() {"the closest embedded bitmap strike"} else {"the default"},
+            table.data.len());
+        auto_height = Some(strike_height.unwrap_or(AUTO_TARGET_HEIGHT));
+        unicode_table = Some(table);
+    }
+    if let Some(reference_file) = &convert_opts.match_coverage {
+        let source = ttf_parser::TtfParser::from_font_path(ttf_file, convert_opts.height)?;
+        let reference = ttf_parser::TtfParser::from_font_path(reference_file, convert_opts.height)?;
+        let mut table = unicode_table.unwrap_or_else(|| unicode_table::UnicodeTable::from_chars(
+            (0..cli_glyph_count.unwrap_or(charset_preset.glyph_count())).filter_map(char::from_u32)));
+        table.retain_graphemes(|g| g.chars().all(|c| source.is_defined(c) && reference.is_defined(c)));
+        if let Some(gc) = cli_glyph_count {
+            table.data.truncate(gc as usize);
         }
-        None => {
-            let glyph_count = {if let Some(n) = cli_glyph_count {n} else {256}};
-            (None, glyph_count, psf2_writer::Psf2GlyphSet::new(ttf_parser, glyph_count, pad)?)
+        log::info!("Codepoints defined in both fonts: {}", table.codepoint_count());
+        unicode_table = Some(table);
+    }
+    let glyph_count = match &unicode_table {
+        Some(uc) => uc.data.len() as u32,
+        None if convert_opts.by_glyph_id => {
+            let glyph_id_count = ttf_parser::TtfParser::glyph_id_count(ttf_file)?;
+            log::info!("Font contains {} glyph ids.", glyph_id_count);
+            cli_glyph_count.map_or(glyph_id_count, |gc| std::cmp::min(gc, glyph_id_count))
         }
+        None => cli_glyph_count.unwrap_or(charset_preset.glyph_count()),
     };
 
-    eprintln!("Glyph count: {}", glyph_count);
+    if matches!(format, OutputFormat::Psf2 | OutputFormat::Psf1 | OutputFormat::CHeader) && glyph_count > max_glyphs {
+        let message = format!("The font has {} glyphs, but the console accepts at most {}; \
+            setfont may reject it. Use --glyph-count or a shorter Unicode table to include fewer glyphs.", 
+            glyph_count, max_glyphs);
+        if strict {
+            return Err(message.into());
+        }
+        log::warn!("{}", message);
+    }
 
-    let header = psf2_writer::Psf2Header{
-        unicode_table_exists: unicode_table_file.is_some(),
-        glyph_count: glyph_count,
-        glyph_size: glyphs.length,
-        glyph_height: glyphs.height,
-        glyph_width: glyphs.width,
+    let characters: Vec<char> = match &unicode_table {
+        Some(uc) => uc.data.iter()
+            .filter_map(|row| convert_opts.render_grapheme.pick(row))
+            .flat_map(|g| g.chars())
+            .collect(),
+        None => (0..glyph_count).filter_map(char::from_u32).collect(),
+    };
+
+    let height = match convert_opts.fit_height {
+        Some(max_height) => {
+            let fit_height = ttf_parser::TtfParser::fit_height(ttf_file, max_height, &characters)?;
+            log::info!("Fit height: {} px", fit_height);
+            fit_height
+        }
+        None => auto_height.unwrap_or(convert_opts.height),
     };
+    let height = match convert_opts.size_metric {
+        ttf_parser::SizeMetric::Cell => height,
+        size_metric => {
+            let cell_height = ttf_parser::TtfParser::metrics(ttf_file)?.cell_height(height, size_metric)
+                .ok_or("The font has no outline for 'H', so its cap height is unknown; try --size-metric em.")?;
+            log::info!("Cell height: {} px", cell_height);
+            cell_height
+        }
+    };
+
+    let ttf_parser = profile.time("Font load", || {
+        let mut ttf_parser = ttf_parser::TtfParser::from_font_path(ttf_file, height)?;
+        for fallback_file in convert_opts.fallback_font.iter() {
+            ttf_parser.add_fallback(fallback_file)?;
+        }
+        ttf_parser.set_fallback_to_fffd(convert_opts.fallback_to_fffd);
+        ttf_parser.set_blank_missing(convert_opts.blank_missing);
+        ttf_parser.set_strike_height_policy(convert_opts.strike_height);
+        ttf_parser.set_strict_compose(convert_opts.strict_compose);
+        ttf_parser.set_cell_width(convert_opts.cell_width);
+        ttf_parser.set_coverage_threshold(convert_opts.threshold);
+        ttf_parser.set_raster_mode(convert_opts.raster_mode);
+        ttf_parser.set_supersample(convert_opts.supersample);
+        ttf_parser.set_vertical_offset(convert_opts.baseline_offset);
+        if convert_opts.cell_width != ttf_parser::CellWidth::Em && !ttf_parser.has_advance_metrics() {
+            log::warn!("Every glyph in the font has zero advance width, so glyphs would be rendered \
+                empty. Using the em width for every glyph instead, as with --cell-width em.");
+            ttf_parser.set_cell_width(ttf_parser::CellWidth::Em);
+        }
+        if !pad && !convert_opts.pad_to_mode_size {
+            ttf_parser.check_fallback_cells()?;
+        }
+        Ok::<_, errors::TtfParserError>(ttf_parser)
+    })?;
+    // --baseline-offset moves the baseline down the cell along with the outlines.
+    let ascent = (ttf_parser.ascent() as i64 + convert_opts.baseline_offset as i64).max(0) as u32;
+    let ascent = match convert_opts.squeeze_v {
+        true => ascent.div_ceil(2),
+        false => ascent,
+    };
+
+    if !convert_opts.by_glyph_id && !ttf_parser.maps_any(&characters) {
+        log::warn!("None of the requested characters map to a glyph in this font, which probably \
+            lacks a Unicode cmap. The font contains {} glyph ids; use --by-glyph-id to convert them directly.",
+            ttf_parser::TtfParser::glyph_id_count(ttf_file)?);
+    }
 
-    let psf2font = psf2_writer::Psf2Font{
-        header,
-        glyphs,
-        unicode_table,
+    // at the default glyph count, a font that only covers ASCII gets mostly placeholder glyphs.
+    // every font leaves the control characters undefined, so only warn if most glyphs are missing.
+    if unicode_table.is_none() && !convert_opts.by_glyph_id && cli_glyph_count.is_none() {
+        let undefined_count = characters.iter().filter(|c| !ttf_parser.is_defined_in_chain(**c)).count();
+        if undefined_count > characters.len() / 2 {
+            log::warn!("{} of the default {} glyphs are not defined in the font, and will be rendered \
+                as placeholders. Use --glyph-count, --match-coverage, or a Unicode table to include fewer glyphs.",
+                undefined_count, glyph_count);
+        }
+    }
+
+    let rendered_glyphs = profile.time_each("Glyph rendering", glyph_count, || match &unicode_table {
+        Some(uc) => psf2_writer::Psf2GlyphSet::render_with_unicode_table(&ttf_parser, uc, convert_opts.render_grapheme, 
+            allow_missing, &mut glyph_timings),
+        None if convert_opts.by_glyph_id => 
+            psf2_writer::Psf2GlyphSet::render_by_glyph_id(&ttf_parser, glyph_count, &mut glyph_timings),
+        None => psf2_writer::Psf2GlyphSet::render(&ttf_parser, glyph_count, &mut glyph_timings),
+    }).map_err(|e| e.to_string())?;
+    let flattened_count = ttf_parser.flattened_glyph_count();
+    if flattened_count > 0 {
+        let method = match convert_opts.raster_mode {
+            ttf_parser::RasterMode::Threshold => 
+                format!("setting pixels at least {:.0}% covered", ttf_parser.coverage_threshold() * 100.0),
+            ttf_parser::RasterMode::Dither => "dithering".to_string(),
+        };
+        log::warn!("{} rasterized outlines were anti-aliased, and were flattened to one bit by {}. Try another \
+            height, --threshold, or --raster-mode, or see `report --diff-vs-strike` for fonts with bitmap strikes.", 
+            flattened_count, method);
+    }
+    let clipped_glyphs = ttf_parser.clipped_glyphs();
+    if !clipped_glyphs.is_empty() {
+        for clipped_glyph in clipped_glyphs.iter() {
+            log::warn!("{}.", clipped_glyph);
+        }
+        let message = format!("{} rasterized outlines were clipped by their cells. Try another height, \
+            --baseline-offset, or --fit-height.", clipped_glyphs.len());
+        if convert_opts.strict_clipping {
+            return Err(message.into());
+        }
+        log::warn!("{}", message);
+    }
+    let rendered_glyphs = match (convert_opts.sort_table, &mut unicode_table) {
+        (true, Some(uc)) => {
+            let mut glyphs: Vec<Option<glyph::Glyph>> = rendered_glyphs.into_iter().map(Some).collect();
+            uc.sort_by_codepoint().into_iter().map(|i| glyphs[i].take().unwrap()).collect()
+        }
+        _ => rendered_glyphs,
+    };
+    let rendered_glyphs = match convert_opts.squeeze_v {
+        true => rendered_glyphs.into_iter().map(glyph::Glyph::squeeze_vertical).collect(),
+        false => rendered_glyphs,
+    };
+    let rendered_glyphs = match convert_opts.width_from_space {
+        true => {
+            let width = ttf_parser.space_width()?;
+            log::info!("Cell width from space: {} px", width);
+            rendered_glyphs.into_iter()
+                .map(|g| {
+                    let height = g.height;
+                    g.resize_canvas(height, width, glyph::HAnchor::Left, glyph::VAnchor::Top)
+                })
+                .collect()
+        }
+        false => rendered_glyphs,
+    };
+    let rendered_glyphs: Vec<glyph::Glyph> = rendered_glyphs.into_iter()
+        .map(|g| effects.iter().fold(g, |g, effect| effect(g)))
+        .collect();
+    let rendered_glyphs: Vec<glyph::Glyph> = match convert_opts.trim {
+        true => {
+            let mut trimmed_columns = 0;
+            let glyphs = rendered_glyphs.into_iter()
+                .map(|g| {
+                    let (g, left, right) = g.trim();
+                    trimmed_columns += left + right;
+                    g
+                })
+                .collect();
+            log::info!("Trimmed {} blank columns.", trimmed_columns);
+            glyphs
+        }
+        false => rendered_glyphs,
     };
-    let output_file = &Path::new(output_file);
-    fs::write(output_file, psf2font.write())?;
-    println!("Wrote PSF2 font file.");
+    let rendered_glyphs = match convert_opts.dedup {
+        true => {
+            let uc = unicode_table.get_or_insert_with(|| unicode_table::UnicodeTable::identity(glyph_count));
+            let glyphs = psf2_writer::Psf2GlyphSet::dedup(rendered_glyphs, uc);
+            log::info!("Merged {} duplicate glyphs.", glyph_count as usize - glyphs.len());
+            glyphs
+        }
+        false => rendered_glyphs,
+    };
+    let glyph_count = rendered_glyphs.len() as u32;
+    log::info!("Glyph count: {}", glyph_count);
+
+    if let Some(unimap_file) = &convert_opts.write_unimap {
+        let unimap = match &unicode_table {
+            Some(uc) => uc.to_unimap(),
+            None => unicode_table::UnicodeTable::identity(glyph_count).to_unimap(),
+        };
+        write_output(unimap_file, unimap.as_bytes(), "unimap file", false, dry_run)?;
+    }
+
+    if format == OutputFormat::ProportionalBin {
+        // proportional glyphs keep their own widths, so there are no dimensions to check.
+        let mut font = profile.time("Byte writing", || {
+            Ok::<_, errors::ProportionalFontError>(proportional::ProportionalFont::new(rendered_glyphs, unicode_table)?.write())
+        })?;
+        if let Some(size) = convert_opts.pad_file {
+            pad_to_file_size(&mut font, size)?;
+        }
+        write_output(output_file, &font, "proportional bitmap font file", compress, dry_run)?;
+        if convert_opts.profile {
+            profile.report();
+        }
+        return Ok(());
+    }
+
+    let glyphs = profile.time("Dimension checking", || {
+        let glyphs = match convert_opts.pad_to_mode_size {
+            true => psf2_writer::Psf2GlyphSet::from_glyphs_pad_to_mode(rendered_glyphs)?,
+            false if pad => psf2_writer::Psf2GlyphSet::from_glyphs_pad_anchored(rendered_glyphs, convert_opts.pad_anchor)?,
+            false => {
+                let histogram = psf2_writer::Psf2GlyphSet::analyze_widths(&rendered_glyphs);
+                psf2_writer::Psf2GlyphSet::from_glyphs(rendered_glyphs, false).inspect_err(|e| {
+                    if let errors::GlyphSetError::InconsistentDimensions{..} = e {
+                        for line in histogram.to_string().lines() {
+                            log::warn!("{}", line);
+                        }
+                        log::warn!("Use --pad or --pad-to-mode-size to give every glyph the same size.");
+                    }
+                })?
+            }
+        };
+        match convert_opts.glyph_gap {
+            Some((right, bottom)) => glyphs.with_gap(right, bottom),
+            None => Ok(glyphs),
+        }
+    })?;
+
+    let ppem = convert_opts.ppem_override.unwrap_or(height);
+    if convert_opts.ppem_override.is_none() {
+        let mismatched_ppems: Vec<u32> = glyphs.glyphs().iter()
+            .map(|g| g.ppem)
+            .filter(|p| *p != ppem)
+            .sorted().dedup()
+            .collect();
+        if !mismatched_ppems.is_empty() {
+            log::warn!("Some glyphs come from embedded bitmap strikes at {} ppem, but the font declares \
+                {} ppem. Use --ppem-override to declare a different size.", mismatched_ppems.iter().join(", "), ppem);
+        }
+    }
+
+    if let Some(svg_file) = &convert_opts.svg {
+        write_output(svg_file, svg_sheet::svg_sheet(glyphs.glyphs()).as_bytes(), "SVG sheet", false, dry_run)?;
+    }
+
+    if convert_opts.dump_rle {
+        for (i, g) in glyphs.glyphs().iter().enumerate() {
+            eprintln!("Glyph {} ({:?}):\n{}\n", i, g.grapheme, g.to_rle_string());
+        }
+    }
+
+    profile.time("Byte writing", || -> Result<(), Box<dyn std::error::Error>> {
+        if format == OutputFormat::PngSheet {
+            let mut png: Vec<u8> = vec![];
+            png_sheet::png_sheet(glyphs.glyphs(), convert_opts.sheet_columns)
+                .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+            write_output(output_file, &png, "PNG sheet", compress, dry_run)?;
+            return Ok(());
+        }
+        if format == OutputFormat::Pff2 {
+            let name = format!("{} {}", 
+                ttf_file.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(), 
+                height);
+            let pff2font = pff2_writer::Pff2Font::new(name, ppem, ascent, glyphs, unicode_table.as_ref());
+            let mut font = pff2font.write();
+            if let Some(size) = convert_opts.pad_file {
+                pad_to_file_size(&mut font, size)?;
+            }
+            write_output(output_file, &font, "PFF2 font file", compress, dry_run)?;
+            return Ok(());
+        }
+        if format == OutputFormat::Psf1 {
+            let mut font = psf1::Psf1Font::new(glyphs.glyphs().to_vec(), unicode_table)
+                .map_err(|e| e.to_string())?.write();
+            if let Some(size) = convert_opts.pad_file {
+                pad_to_file_size(&mut font, size)?;
+            }
+            write_output(output_file, &font, "PSF1 font file", compress, dry_run)?;
+            return Ok(());
+        }
+
+        let header = psf2_writer::Psf2Header{
+            unicode_table_exists: unicode_table.is_some(),
+            glyph_count,
+            glyph_size: glyphs.length,
+            glyph_height: glyphs.height,
+            glyph_width: glyphs.width,
+        };
+
+        let psf2font = psf2_writer::Psf2Font{
+            header,
+            glyphs,
+            unicode_table,
+        };
+        if dry_run {
+            report::print_psf2_header(&psf2_reader::read_psf2_header(&psf2font.write())?);
+        }
+        let output_file = &Path::new(output_file);
+        if format == OutputFormat::CHeader {
+            let name = c_header::c_identifier(&output_file.file_stem().unwrap_or_default().to_string_lossy());
+            write_output(output_file, c_header::c_header(&psf2font, &name).as_bytes(), "C header file", compress, dry_run)?;
+            return Ok(());
+        }
+        let mut font = psf2font.write();
+        if let Some(size) = convert_opts.pad_file {
+            pad_to_file_size(&mut font, size)?;
+        }
+        write_output(output_file, &font, "PSF2 font file", compress, dry_run)?;
+        Ok(())
+    })?;
+
+    if convert_opts.profile {
+        profile.report();
+    }
+    if convert_opts.report_timing_per_glyph.is_some() {
+        glyph_timings.report();
+    }
     Ok(())
 }
+
+/// Writes `contents` to `path`, or to stdout if `path` is `-`, and reports it on stderr. If
+/// `compress` is set, `contents` are gzipped first. With `dry_run`, only reports on stdout how many
+/// bytes would have been written.
+fn write_output(path: &Path, contents: &[u8], description: &str, compress: bool, dry_run: bool) 
+    -> std::io::Result<()> {
+    let compressed;
+    let (contents, description) = match compress {
+        true => {
+            compressed = gzip::compress(contents)?;
+            (&compressed[..], format!("gzipped {}", description))
+        }
+        false => (contents, description.to_string()),
+    };
+    if dry_run {
+        println!("Dry run: would write {} ({} bytes) to {}.", description, contents.len(), path.display());
+        return Ok(());
+    }
+    if path == Path::new(STDOUT_PATH) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(contents)?;
+        stdout.flush()?;
+        log::info!("Wrote {} to stdout.", description);
+        return Ok(());
+    }
+    fs::write(path, contents)?;
+    log::info!("Wrote {}.", description);
+    return Ok(());
+}
+
+/// Reads a font file, decompressing it first if it is gzipped.
+fn read_font_file(path: &Path) -> std::io::Result<Vec<u8>> {
+    return gzip::decompress_if_gzipped(fs::read(path)?);
+}
+
+/// Appends zero bytes to `font` until it is exactly `size` bytes long. Returns an error if `font`
+/// is already longer than `size`.
+fn pad_to_file_size(font: &mut Vec<u8>, size: usize) -> Result<(), String> {
+    if font.len() > size {
+        return Err(format!("The font is {} bytes long, which exceeds the --pad-file size of {} bytes.", 
+            font.len(), size));
+    }
+    font.resize(size, 0);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pad_file_gives_exact_size() {
+        let mut font = vec![0x72, 0xb5, 0x4a, 0x86, 1, 2, 3];
+        pad_to_file_size(&mut font, 16).unwrap();
+        assert_eq!(font.len(), 16);
+        assert_eq!(&font[..7], [0x72, 0xb5, 0x4a, 0x86, 1, 2, 3]);
+        assert!(font[7..].iter().all(|b| *b == 0));
+        // a font that is already the right size is unchanged.
+        pad_to_file_size(&mut font, 16).unwrap();
+        assert_eq!(font.len(), 16);
+    }
+
+    #[test]
+    fn fit_height_conflicts_with_canvas_options() {
+        let convert = ["otf2psf", "convert", "in.ttf", "out.psf", "--fit-height", "24"];
+        assert!(Cli::try_parse_from(convert).is_ok());
+        for extra in [["--baseline-offset", "1"], ["--cell-width", "em"], ["--supersample", "2"],
+            ["--fallback-font", "other.ttf"]] {
+            assert!(Cli::try_parse_from(convert.iter().chain(extra.iter())).is_err(), "{:?}", extra);
+        }
+    }
+
+    #[test]
+    fn deterministic_blank_is_an_alias_of_blank_missing() {
+        let cli = Cli::try_parse_from(["otf2psf", "convert", "in.ttf", "out.psf", "--deterministic-blank"]).unwrap();
+        let Command::Convert(convert_opts) = cli.command else { panic!("not a convert command") };
+        assert!(convert_opts.blank_missing);
+    }
+
+    #[test]
+    fn pad_file_smaller_than_font_is_an_error() {
+        let mut font = vec![1; 32];
+        assert!(pad_to_file_size(&mut font, 31).is_err());
+        assert_eq!(font.len(), 32);
+    }
+}
@@ -0,0 +1,222 @@
+use bitvec::prelude::*;
+
+use crate::glyph::Glyph;
+use crate::psf2_writer::Psf2GlyphSet;
+use crate::unicode_table::UnicodeTable;
+
+/// Section length used by GRUB for the DATA section, which always runs to the end of the file.
+const PFF2_DATA_SECTION_LENGTH: u32 = 0xffff_ffff;
+/// The length of each entry in the CHIX (character index) section, in bytes.
+const PFF2_CHIX_ENTRY_SIZE: u32 = 9;
+/// Storage flags for an uncompressed glyph in a CHIX entry.
+const PFF2_STORAGE_UNCOMPRESSED: u8 = 0;
+
+/// A GRUB PFF2 font, built from a uniform-cell glyph set.
+///
+/// PFF2 stores per-glyph dimensions and offsets, but since our glyphs all share one cell, every
+/// glyph is written with the same width and height. Each glyph is placed by its own x- and
+/// y-offsets where it has them (rasterized outlines and embedded bitmaps do), and otherwise at the
+/// origin on the cell's descent.
+pub struct Pff2Font {
+    /// The full name of the font, e.g. "Unifont Regular 16".
+    pub name: String,
+    /// The nominal point size of the font: the pixels-per-em size the font was rendered at,
+    /// unless overridden.
+    pub point_size: u16,
+    /// The number of pixels in the cell above the baseline.
+    pub ascent: u16,
+    /// The number of pixels in the cell below the baseline.
+    pub descent: u16,
+    /// The glyph cell width, in pixels.
+    pub width: u16,
+    /// The glyph cell height, in pixels.
+    pub height: u16,
+    /// Pairs of Unicode codepoints and the glyphs that represent them, sorted by codepoint.
+    entries: Vec<(u32, Glyph)>,
+}
+
+impl Pff2Font {
+    /// Builds a PFF2 font from a glyph set. If a Unicode table is provided, every single-codepoint
+    /// grapheme in a table entry is mapped to that entry's glyph; multi-codepoint sequences cannot
+    /// be represented in PFF2 and are skipped. Without a table, glyph `i` represents `U+i`.
+    /// `point_size` is the PPEM declared in the `PTSZ` section.
+    pub fn new(name: String, point_size: u32, ascent: u32, glyph_set: Psf2GlyphSet, 
+        unicode_table: Option<&UnicodeTable>) -> Self {
+        let height = glyph_set.height;
+        let width = glyph_set.width;
+        let ascent = std::cmp::min(ascent, height);
+
+        let mut entries: Vec<(u32, Glyph)> = vec![];
+        for (i, glyph) in glyph_set.into_glyphs().into_iter().enumerate() {
+            match unicode_table {
+                Some(uc) => {
+                    for grapheme in uc.data[i].iter() {
+                        let mut chars = grapheme.chars();
+                        if let (Some(c), None) = (chars.next(), chars.next()) {
+                            entries.push((u32::from(c), glyph.clone()));
+                        }
+                    }
+                }
+                // surrogate codepoints are not characters, so their (blank) glyphs are skipped.
+                None if char::from_u32(i as u32).is_none() => (),
+                None => entries.push((i as u32, glyph)),
+            }
+        }
+
+        // GRUB looks up characters with a binary search over the index, so it must be sorted and
+        // free of duplicates. If a codepoint appears more than once, the first glyph wins.
+        entries.sort_by_key(|(codepoint, _)| *codepoint);
+        entries.dedup_by_key(|(codepoint, _)| *codepoint);
+
+        return Self{
+            name,
+            point_size: point_size as u16,
+            ascent: ascent as u16,
+            descent: (height - ascent) as u16,
+            width: width as u16,
+            height: height as u16,
+            entries,
+        };
+    }
+
+    /// Writes the PFF2 font to a vector of bytes.
+    pub fn write(self) -> Vec<u8> {
+        let mut font: Vec<u8> = vec![];
+        font.extend(section(b"FILE", b"PFF2"));
+        let mut name = self.name.into_bytes();
+        name.push(0);
+        font.extend(section(b"NAME", &name));
+        font.extend(section(b"PTSZ", &self.point_size.to_be_bytes()));
+        font.extend(section(b"MAXW", &self.width.to_be_bytes()));
+        font.extend(section(b"MAXH", &self.height.to_be_bytes()));
+        font.extend(section(b"ASCE", &self.ascent.to_be_bytes()));
+        font.extend(section(b"DESC", &self.descent.to_be_bytes()));
+
+        let glyph_data: Vec<Vec<u8>> = self.entries.iter()
+            .map(|(_, g)| pack_glyph(g, self.descent))
+            .collect();
+
+        // glyph offsets in the CHIX section are absolute file offsets, so we need to know where
+        // the DATA section will start before writing the index.
+        let chix_length = PFF2_CHIX_ENTRY_SIZE * self.entries.len() as u32;
+        let mut offset = font.len() as u32 + 8 + chix_length + 8;
+
+        let mut chix: Vec<u8> = vec![];
+        for ((codepoint, _), data) in self.entries.iter().zip(glyph_data.iter()) {
+            chix.extend(codepoint.to_be_bytes());
+            chix.push(PFF2_STORAGE_UNCOMPRESSED);
+            chix.extend(offset.to_be_bytes());
+            offset += data.len() as u32;
+        }
+        font.extend(section(b"CHIX", &chix));
+
+        font.extend(b"DATA");
+        font.extend(PFF2_DATA_SECTION_LENGTH.to_be_bytes());
+        font.extend(glyph_data.into_iter().flatten());
+        return font;
+    }
+}
+
+/// Writes a PFF2 section: a four-byte name, a big-endian length, and the section data.
+fn section(name: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut section = name.to_vec();
+    section.extend((data.len() as u32).to_be_bytes());
+    section.extend(data);
+    return section;
+}
+
+/// Writes a PFF2 glyph entry: width, height, x-offset, y-offset, and device width (all
+/// big-endian 16-bit), followed by the bitmap. PFF2 bitmaps are packed bit-by-bit with no
+/// padding between rows, and padded to a whole byte at the end.
+fn pack_glyph(glyph: &Glyph, descent: u16) -> Vec<u8> {
+    let mut entry: Vec<u8> = vec![];
+    entry.extend((glyph.width as u16).to_be_bytes());
+    entry.extend((glyph.height as u16).to_be_bytes());
+    // the x-offset is the position of the left edge of the bitmap relative to the origin, and the
+    // y-offset that of its bottom edge relative to the baseline, counting up.
+    let x_offset = glyph.x_offset.unwrap_or(0) as i16;
+    entry.extend(x_offset.to_be_bytes());
+    let y_offset = glyph.y_offset.map_or(-(descent as i16), |o| o as i16);
+    entry.extend(y_offset.to_be_bytes());
+    entry.extend((glyph.width as u16).to_be_bytes());
+
+    let row_length = (glyph.width as f64 / 8.0).ceil() as usize;
+    let mut bitmap = bitvec![u8, Msb0;];
+    if row_length > 0 {
+        for row in glyph.data.chunks_exact(row_length) {
+            bitmap.extend_from_bitslice(&row.view_bits::<Msb0>()[..glyph.width as usize]);
+        }
+    }
+    entry.extend(bitmap.into_vec());
+    return entry;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::GlyphTimings;
+    use crate::ttf_parser::{CellWidth, TtfParser};
+
+    /// The width, height, x-offset, y-offset, and device width of the first glyph entry in the
+    /// DATA section.
+    fn first_glyph_metrics(font: &[u8]) -> (u16, u16, i16, i16, u16) {
+        let data = font.windows(4).position(|w| w == b"DATA").unwrap() + 8;
+        let field = |i: usize| [font[data + 2 * i], font[data + 2 * i + 1]];
+        return (u16::from_be_bytes(field(0)), u16::from_be_bytes(field(1)), i16::from_be_bytes(field(2)), 
+            i16::from_be_bytes(field(3)), u16::from_be_bytes(field(4)));
+    }
+
+    fn section_u16(font: &[u8], name: &[u8; 4]) -> u16 {
+        let start = font.windows(4).position(|w| w == name).unwrap() + 8;
+        return u16::from_be_bytes([font[start], font[start + 1]]);
+    }
+
+    fn render_a(ttf_parser: &TtfParser) -> Vec<u8> {
+        let table = UnicodeTable::from_chars(['A']);
+        let glyphs = Psf2GlyphSet::render_with_unicode_table(ttf_parser, &table, 
+            crate::psf2_writer::RenderGrapheme::First, false, &mut GlyphTimings::new(0)).unwrap();
+        let glyph_set = Psf2GlyphSet::from_glyphs(glyphs, false).unwrap();
+        return Pff2Font::new(String::from("Demo 16"), 16, ttf_parser.ascent(), glyph_set, Some(&table)).write();
+    }
+
+    #[test]
+    fn outline_glyph_sits_on_the_baseline() {
+        let ttf_parser = TtfParser::from_bytes(include_bytes!("../fixtures/demo.ttf").to_vec(), 16).unwrap();
+        let ascent = ttf_parser.ascent();
+        let font = render_a(&ttf_parser);
+        let (width, height, x_offset, y_offset, device_width) = first_glyph_metrics(&font);
+        assert_eq!((height, x_offset, device_width), (16, 0, width));
+        assert_eq!(y_offset, ascent as i16 - 16);
+        assert_eq!(section_u16(&font, b"ASCE"), ascent as u16);
+        assert_eq!(section_u16(&font, b"DESC"), 16 - ascent as u16);
+    }
+
+    #[test]
+    fn offsets_follow_baseline_offset_and_ink_canvas() {
+        let mut ttf_parser = TtfParser::from_bytes(include_bytes!("../fixtures/demo.ttf").to_vec(), 16).unwrap();
+        let ascent = ttf_parser.ascent();
+        ttf_parser.set_vertical_offset(2);
+        ttf_parser.set_cell_width(CellWidth::Ink);
+        let left_bearing = ttf_parser.render_char('A').unwrap().bearing_x.unwrap();
+        let (_, _, x_offset, y_offset, _) = first_glyph_metrics(&render_a(&ttf_parser));
+        assert_eq!(y_offset, ascent as i16 + 2 - 16);
+        assert_eq!(x_offset, left_bearing as i16);
+    }
+
+    #[test]
+    fn accent_has_higher_y_offset_than_letter() {
+        // embedded bitmaps are cropped to their ink, with their bottom edge's height above the
+        // baseline as their y-offset.
+        let letter = Glyph{y_offset: Some(0), ..Glyph::from_ascii_art(&[".#.", "#.#", "###", "#.#"], String::from("A"), 8).unwrap()};
+        let accent = Glyph{y_offset: Some(6), ..Glyph::from_ascii_art(&["..#", ".#."], String::from("\u{b4}"), 8).unwrap()};
+        let glyph_set = Psf2GlyphSet::from_glyphs(vec![letter, accent], true).unwrap();
+        let table = UnicodeTable::from_chars(['A', '\u{b4}']);
+        let font = Pff2Font::new(String::from("Test 8"), 8, 6, glyph_set, Some(&table));
+        let y_offsets: Vec<i16> = font.entries.iter().map(|(_, g)| g.y_offset.unwrap() as i16).collect();
+        // padding the accent's canvas down to the letter's height lowers its bottom edge by 2 rows.
+        assert_eq!(y_offsets, [0, 4]);
+        let bytes = font.write();
+        let (_, height, _, letter_y_offset, _) = first_glyph_metrics(&bytes);
+        assert_eq!((height, letter_y_offset), (4, 0));
+    }
+}
@@ -0,0 +1,38 @@
+use image::{GrayImage, Luma};
+
+use crate::glyph::Glyph;
+
+/// The width of the lines between glyph cells in a PNG sheet, in pixels.
+const PNG_SHEET_SEPARATOR: u32 = 1;
+/// The shade of a set glyph pixel in a PNG sheet.
+const PNG_SHEET_INK: Luma<u8> = Luma([0]);
+/// The shade of a blank glyph pixel in a PNG sheet.
+const PNG_SHEET_PAPER: Luma<u8> = Luma([255]);
+/// The shade of the lines between glyph cells in a PNG sheet.
+const PNG_SHEET_SEPARATOR_SHADE: Luma<u8> = Luma([160]);
+
+/// Draws `glyphs` as a grayscale image: a grid `columns` cells wide, in glyph order, with each
+/// cell the size of the largest glyph and gray lines between and around the cells.
+pub fn png_sheet(glyphs: &[Glyph], columns: usize) -> GrayImage {
+    let cell_width = glyphs.iter().map(|g| g.width).max().unwrap_or(0);
+    let cell_height = glyphs.iter().map(|g| g.height).max().unwrap_or(0);
+    let columns = std::cmp::max(std::cmp::min(glyphs.len(), columns), 1) as u32;
+    let rows = glyphs.len().div_ceil(columns as usize) as u32;
+
+    let mut sheet = GrayImage::from_pixel(
+        columns * (cell_width + PNG_SHEET_SEPARATOR) + PNG_SHEET_SEPARATOR,
+        rows * (cell_height + PNG_SHEET_SEPARATOR) + PNG_SHEET_SEPARATOR,
+        PNG_SHEET_SEPARATOR_SHADE,
+    );
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let left = (i as u32 % columns) * (cell_width + PNG_SHEET_SEPARATOR) + PNG_SHEET_SEPARATOR;
+        let top = (i as u32 / columns) * (cell_height + PNG_SHEET_SEPARATOR) + PNG_SHEET_SEPARATOR;
+        for y in 0..cell_height {
+            for x in 0..cell_width {
+                let set = x < glyph.width && y < glyph.height && glyph.get_pixel(x, y);
+                sheet.put_pixel(left + x, top + y, if set {PNG_SHEET_INK} else {PNG_SHEET_PAPER});
+            }
+        }
+    }
+    return sheet;
+}
@@ -0,0 +1,89 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+/// Wall-clock timings for the phases of a conversion, reported with `--profile`.
+#[derive(Default)]
+pub struct Profile {
+    /// Each phase's name, its duration, and the number of items it processed, if counted.
+    phases: Vec<(&'static str, Duration, Option<u32>)>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        return Self{phases: vec![]};
+    }
+
+    /// Runs `f`, recording how long it took as `phase`.
+    pub fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push((phase, start.elapsed(), None));
+        return result;
+    }
+
+    /// Runs `f`, which processes `count` items, recording how long it took as `phase`. The report
+    /// includes the average time per item.
+    pub fn time_each<T>(&mut self, phase: &'static str, count: u32, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push((phase, start.elapsed(), Some(count)));
+        return result;
+    }
+
+    /// Prints the time taken by each recorded phase to stderr.
+    pub fn report(&self) {
+        eprintln!("Profile:");
+        for (phase, duration, count) in self.phases.iter() {
+            match count {
+                Some(c) if *c > 0 => eprintln!("  {}: {:.3?} ({:.3?} per glyph)", phase, duration, *duration / *c),
+                _ => eprintln!("  {}: {:.3?}", phase, duration),
+            }
+        }
+        let total: Duration = self.phases.iter().map(|(_, d, _)| *d).sum();
+        eprintln!("  Total: {:.3?}", total);
+    }
+}
+
+/// The render times of the slowest glyphs in a conversion, reported with
+/// `--report-timing-per-glyph`.
+pub struct GlyphTimings {
+    /// The number of glyphs to keep.
+    count: usize,
+    /// The slowest glyphs seen so far and their render times, as a min-heap so that the fastest
+    /// of them is the one evicted.
+    slowest: BinaryHeap<Reverse<(Duration, String)>>,
+}
+
+impl GlyphTimings {
+    /// Keeps the `count` slowest glyphs. If `count` is 0, nothing is recorded.
+    pub fn new(count: usize) -> Self {
+        return Self{count, slowest: BinaryHeap::new()};
+    }
+
+    /// Runs `f`, which renders one glyph, and records how long it took. `label` describes the
+    /// glyph, and is only called if the glyph is among the slowest.
+    pub fn time<T>(&mut self, label: impl FnOnce() -> String, f: impl FnOnce() -> T) -> T {
+        if self.count == 0 {
+            return f();
+        }
+        let start = Instant::now();
+        let result = f();
+        let duration = start.elapsed();
+        if self.slowest.len() < self.count {
+            self.slowest.push(Reverse((duration, label())));
+        } else if self.slowest.peek().is_some_and(|Reverse((fastest, _))| duration > *fastest) {
+            self.slowest.pop();
+            self.slowest.push(Reverse((duration, label())));
+        }
+        return result;
+    }
+
+    /// Prints the slowest glyphs, slowest first, to stderr.
+    pub fn report(self) {
+        eprintln!("Slowest glyphs:");
+        for Reverse((duration, label)) in self.slowest.into_sorted_vec() {
+            eprintln!("  {}: {:.3?}", label, duration);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::errors::ProportionalFontError;
+use crate::glyph::Glyph;
+use crate::psf2_reader::read_unicode_table;
+use crate::unicode_table::UnicodeTable;
+
+pub const PROPORTIONAL_MAGIC_BYTES: [u8; 4] = *b"PBF1";
+const PROPORTIONAL_HEADER_SIZE: usize = 16;
+const PROPORTIONAL_HAS_UNICODE_TABLE: u32 = 0x01;
+
+/// A proportional bitmap font, in a simple binary format for custom renderers. Its glyphs share a
+/// height, but each have their own width. All integers are little-endian:
+///
+/// - a 16-byte header: the magic bytes `PBF1`, then the glyph count, the glyph height in pixels,
+///   and flags, each a `u32`. Flag bit 0 is set if a Unicode table follows the glyphs.
+/// - each glyph in turn: its width in pixels as one byte, then `height` rows of bitmap, each
+///   padded to a whole byte as in PSF2.
+/// - if flagged, a Unicode table in the PSF2 format.
+pub struct ProportionalFont {
+    /// The height in pixels of every glyph.
+    pub height: u32,
+    pub glyphs: Vec<Glyph>,
+    pub unicode_table: Option<UnicodeTable>,
+}
+
+impl ProportionalFont {
+    /// Builds a font from rendered glyphs. Returns an error if the glyphs differ in height, or if
+    /// any glyph is too wide for its width to fit in a byte.
+    pub fn new(mut glyphs: Vec<Glyph>, unicode_table: Option<UnicodeTable>) -> Result<Self, ProportionalFontError> {
+        let height = glyphs.first().map_or(0, |g| g.height);
+        for g in glyphs.iter_mut() {
+            if g.height != height {
+                return Err(ProportionalFontError::InconsistentHeights{height: g.height, expected_height: height});
+            }
+            if g.width > u8::MAX as u32 {
+                return Err(ProportionalFontError::TooWide{width: g.width, max_width: u8::MAX as u32});
+            }
+            g.clear_padding_bits();
+        }
+        return Ok(Self{height, glyphs, unicode_table});
+    }
+
+    /// Reads a font from the contents of a file in this format.
+    pub fn read(bytes: &[u8]) -> Result<Self, ProportionalFontError> {
+        if bytes.len() < PROPORTIONAL_HEADER_SIZE {
+            return Err(ProportionalFontError::TooShort{length: bytes.len(), expected_length: PROPORTIONAL_HEADER_SIZE});
+        }
+        if bytes[0..4] != PROPORTIONAL_MAGIC_BYTES {
+            return Err(ProportionalFontError::BadMagic);
+        }
+        let field = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let (glyph_count, height, flags) = (field(4), field(8), field(12));
+
+        let mut glyphs: Vec<Glyph> = vec![];
+        let mut offset = PROPORTIONAL_HEADER_SIZE;
+        for i in 0..glyph_count {
+            let width = *bytes.get(offset)
+                .ok_or(ProportionalFontError::TooShort{length: bytes.len(), expected_length: offset + 1})? as u32;
+            let length = width.div_ceil(8) as usize * height as usize;
+            let data = bytes.get(offset + 1..offset + 1 + length)
+                .ok_or(ProportionalFontError::TooShort{length: bytes.len(), expected_length: offset + 1 + length})?;
+            let mut glyph = Glyph::blank(height, width, char::from_u32(i).map(String::from).unwrap_or_default(), height);
+            glyph.data = data.to_vec();
+            glyphs.push(glyph);
+            offset += 1 + length;
+        }
+
+        let unicode_table = match flags & PROPORTIONAL_HAS_UNICODE_TABLE != 0 {
+            true => Some(read_unicode_table(&bytes[offset..], glyph_count)?),
+            false => None,
+        };
+        if let Some(uc) = &unicode_table {
+            for (glyph, group) in glyphs.iter_mut().zip(uc.data.iter()) {
+                glyph.grapheme = group.first().cloned().unwrap_or_default();
+            }
+        }
+        return Ok(Self{height, glyphs, unicode_table});
+    }
+
+    /// Writes the font to a vector of bytes.
+    pub fn write(self) -> Vec<u8> {
+        let flags = if self.unicode_table.is_some() {PROPORTIONAL_HAS_UNICODE_TABLE} else {0};
+        let mut font: Vec<u8> = PROPORTIONAL_MAGIC_BYTES.to_vec();
+        font.extend((self.glyphs.len() as u32).to_le_bytes());
+        font.extend(self.height.to_le_bytes());
+        font.extend(flags.to_le_bytes());
+        for glyph in self.glyphs.into_iter() {
+            font.push(glyph.width as u8);
+            font.extend(glyph.data);
+        }
+        if let Some(uc) = self.unicode_table {
+            font.extend(uc.write());
+        }
+        return font;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyphs() -> Vec<Glyph> {
+        let glyph = |rows: &[&str], grapheme: &str| Glyph::from_ascii_art(rows, grapheme.to_string(), 2).unwrap();
+        return vec![
+            glyph(&["#.", ".#"], "\u{0}"),
+            glyph(&["#...#.#..#", ".#.#...##."], "\u{1}"),
+            glyph(&["#", "#"], "\u{2}"),
+        ];
+    }
+
+    #[test]
+    fn round_trips_glyphs_of_each_width() {
+        let data = vec![vec!["i".to_string()], vec![], vec!["l".to_string(), "|".to_string()]];
+        for data in [None, Some(data)] {
+            let unicode_table = data.clone().map(|data| UnicodeTable{data, meta: Default::default(), pinned_len: 0});
+            let bytes = ProportionalFont::new(glyphs(), unicode_table).unwrap().write();
+            let font = ProportionalFont::read(&bytes).unwrap();
+            assert_eq!(font.height, 2);
+            assert_eq!(font.glyphs.iter().map(|g| g.width).collect::<Vec<_>>(), [2, 10, 1]);
+            for (read, written) in font.glyphs.iter().zip(glyphs()) {
+                assert_eq!(read.data, written.data);
+            }
+            assert_eq!(font.unicode_table.as_ref().map(|uc| &uc.data), data.as_ref());
+            assert_eq!(font.write(), bytes);
+        }
+    }
+}
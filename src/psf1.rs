@@ -0,0 +1,211 @@
+use crate::errors::{GlyphSetError, Psf1ReadError, Psf1WriteError};
+use crate::glyph::Glyph;
+use crate::psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header};
+use crate::unicode_table::{UnicodeTable, UnicodeTableMeta};
+
+const PSF1_MAGIC_BYTES: [u8; 2] = [0x36, 0x04];
+const PSF1_HEADER_SIZE: usize = 4;
+/// The glyph counts a PSF1 font can have: 256, or 512 with `PSF1_MODE512`.
+const PSF1_GLYPH_COUNTS: [usize; 2] = [256, 512];
+/// Every PSF1 glyph is one byte wide.
+const PSF1_GLYPH_WIDTH: u32 = 8;
+/// Set in the mode byte if the font has 512 glyphs rather than 256.
+const PSF1_MODE512: u8 = 0x01;
+/// Set in the mode byte if a Unicode table follows the glyphs.
+const PSF1_MODEHASTAB: u8 = 0x02;
+/// Set in the mode byte if the Unicode table contains multi-codepoint sequences. Also implies a
+/// Unicode table.
+const PSF1_MODEHASSEQ: u8 = 0x04;
+/// Starts a multi-codepoint sequence in a PSF1 Unicode table entry.
+const PSF1_SEPARATOR: u16 = 0xfffe;
+/// Terminates a PSF1 Unicode table entry.
+const PSF1_TERMINATOR: u16 = 0xffff;
+
+/// Header information for a PSF1 font file.
+pub struct Psf1Header {
+    /// Whether the font has 512 glyphs rather than 256.
+    pub mode512: bool,
+    pub unicode_table_exists: bool,
+    /// Whether the Unicode table contains multi-codepoint sequences.
+    pub has_sequences: bool,
+    /// The height in pixels of every glyph, which is also its size in bytes.
+    pub charsize: u8,
+}
+
+impl Psf1Header {
+    /// Writes the PSF1 header to an array of bytes.
+    pub fn write(self) -> [u8; 4] {
+        let mut mode = 0;
+        if self.mode512 {
+            mode |= PSF1_MODE512;
+        }
+        if self.unicode_table_exists {
+            mode |= PSF1_MODEHASTAB;
+        }
+        if self.has_sequences {
+            mode |= PSF1_MODEHASSEQ;
+        }
+        return [PSF1_MAGIC_BYTES[0], PSF1_MAGIC_BYTES[1], mode, self.charsize];
+    }
+}
+
+/// A PSF1 console font: 256 or 512 glyphs, each 8 pixels wide, with an optional Unicode table
+/// whose entries are UCS-2 codepoints.
+pub struct Psf1Font {
+    /// The height in pixels of every glyph, which is also its size in bytes.
+    pub charsize: u8,
+    pub glyphs: Vec<Glyph>,
+    pub unicode_table: Option<UnicodeTable>,
+}
+
+impl Psf1Font {
+    /// Builds a font from glyphs of equal size, adding blank glyphs (and empty Unicode table
+    /// entries) to fill out 256 or 512 glyphs. Returns an error if the glyphs are not 8 pixels
+    /// wide, are too tall for `charsize`, or are too many, or if the Unicode table has a codepoint
+    /// beyond U+FFFF.
+    pub fn new(mut glyphs: Vec<Glyph>, mut unicode_table: Option<UnicodeTable>) -> Result<Self, Psf1WriteError> {
+        let (height, width) = glyphs.first().map_or((0, PSF1_GLYPH_WIDTH), |g| (g.height, g.width));
+        if width != PSF1_GLYPH_WIDTH {
+            return Err(Psf1WriteError::WrongWidth{width});
+        }
+        let charsize = u8::try_from(height)
+            .map_err(|_| Psf1WriteError::TooTall{height, max_height: u8::MAX as u32})?;
+        let glyph_count = *PSF1_GLYPH_COUNTS.iter().find(|c| **c >= glyphs.len())
+            .ok_or(Psf1WriteError::TooManyGlyphs{count: glyphs.len(), max_count: PSF1_GLYPH_COUNTS[1]})?;
+        if let Some(uc) = &mut unicode_table {
+            if let Some(grapheme) = uc.data.iter().flatten().find(|g| g.chars().any(|c| u32::from(c) > 0xffff)) {
+                return Err(Psf1WriteError::NotUcs2{grapheme: grapheme.clone()});
+            }
+            uc.data.resize(glyph_count, vec![]);
+        }
+        glyphs.resize_with(glyph_count, || Glyph::blank(height, width, String::new(), height));
+        return Ok(Self{charsize, glyphs, unicode_table});
+    }
+
+    /// Writes the font to a vector of bytes.
+    pub fn write(self) -> Vec<u8> {
+        let header = Psf1Header{
+            mode512: self.glyphs.len() > PSF1_GLYPH_COUNTS[0],
+            unicode_table_exists: self.unicode_table.is_some(),
+            has_sequences: self.unicode_table.as_ref()
+                .is_some_and(|uc| uc.data.iter().flatten().any(|g| g.chars().count() > 1)),
+            charsize: self.charsize,
+        };
+        let mut font: Vec<u8> = header.write().to_vec();
+        for glyph in self.glyphs.into_iter() {
+            font.extend(glyph.data);
+        }
+        if let Some(uc) = &self.unicode_table {
+            font.extend(write_psf1_unicode_table(uc));
+        }
+        return font;
+    }
+
+    /// Reads a PSF1 font from the contents of a font file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Psf1ReadError> {
+        if bytes.len() < PSF1_HEADER_SIZE {
+            return Err(Psf1ReadError::TooShort{length: bytes.len(), expected_length: PSF1_HEADER_SIZE});
+        }
+        if bytes[0..2] != PSF1_MAGIC_BYTES {
+            return Err(Psf1ReadError::BadMagic);
+        }
+        let (mode, charsize) = (bytes[2], bytes[3]);
+        let glyph_count = PSF1_GLYPH_COUNTS[(mode & PSF1_MODE512 != 0) as usize];
+        let glyphs_end = PSF1_HEADER_SIZE + glyph_count * charsize as usize;
+        if bytes.len() < glyphs_end {
+            return Err(Psf1ReadError::TooShort{length: bytes.len(), expected_length: glyphs_end});
+        }
+
+        let unicode_table = match mode & (PSF1_MODEHASTAB | PSF1_MODEHASSEQ) != 0 {
+            true => Some(read_unicode_table(&bytes[glyphs_end..], glyph_count)?),
+            false => None,
+        };
+
+        let glyphs: Vec<Glyph> = (0..glyph_count)
+            .map(|i| Glyph{
+                height: charsize as u32,
+                width: PSF1_GLYPH_WIDTH,
+                data: bytes[PSF1_HEADER_SIZE + i * charsize as usize..][..charsize as usize].to_vec(),
+                grapheme: match &unicode_table {
+                    Some(uc) => uc.data[i].first().cloned().unwrap_or_default(),
+                    None => char::from_u32(i as u32).map(String::from).unwrap_or_default(),
+                },
+                bearing_x: None,
+                bearing_y: None,
+                x_offset: None,
+                y_offset: None,
+                // PSF1 does not record the size the glyphs were rendered at.
+                ppem: charsize as u32,
+            })
+            .collect();
+
+        return Ok(Self{charsize, glyphs, unicode_table});
+    }
+}
+
+impl Psf2Font {
+    /// Upgrades a PSF1 font to PSF2, keeping its glyphs and Unicode table.
+    pub fn from_psf1(font: Psf1Font) -> Result<Psf2Font, GlyphSetError> {
+        let header = Psf2Header{
+            unicode_table_exists: font.unicode_table.is_some(),
+            glyph_count: font.glyphs.len() as u32,
+            glyph_size: font.charsize as u32,
+            glyph_height: font.charsize as u32,
+            glyph_width: PSF1_GLYPH_WIDTH,
+        };
+        let glyphs = Psf2GlyphSet::from_glyphs(font.glyphs, false)?;
+        return Ok(Psf2Font{header, glyphs, unicode_table: font.unicode_table});
+    }
+}
+
+/// Reads `glyph_count` PSF1 Unicode table entries. Each entry is a run of little-endian UCS-2
+/// codepoints: single codepoints, then multi-codepoint sequences each preceded by
+/// `PSF1_SEPARATOR`, terminated by `PSF1_TERMINATOR`.
+fn read_unicode_table(bytes: &[u8], glyph_count: usize) -> Result<UnicodeTable, Psf1ReadError> {
+    let mut values = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let mut data: Vec<Vec<String>> = vec![];
+    for glyph_index in 0..glyph_count {
+        let mut entry: Vec<u16> = vec![];
+        loop {
+            match values.next() {
+                Some(PSF1_TERMINATOR) => break,
+                Some(value) => entry.push(value),
+                None => return Err(Psf1ReadError::TruncatedUnicodeTable{glyph_index}),
+            }
+        }
+        let mut segments = entry.split(|v| *v == PSF1_SEPARATOR);
+        let decode = |segment: &[u16]| segment.iter()
+            .map(|v| char::from_u32(*v as u32))
+            .collect::<Option<String>>()
+            .ok_or(Psf1ReadError::InvalidUnicodeTable{glyph_index});
+
+        // the first segment is a run of single-codepoint graphemes; every later segment is one
+        // multi-codepoint grapheme.
+        let mut graphemes: Vec<String> = decode(segments.next().unwrap_or_default())?
+            .chars().map(String::from).collect();
+        for segment in segments {
+            graphemes.push(decode(segment)?);
+        }
+        data.push(graphemes);
+    }
+    return Ok(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0});
+}
+
+/// Writes a Unicode table in the PSF1 layout read by `read_unicode_table`: for each glyph, its
+/// single codepoints, then its multi-codepoint sequences each preceded by `PSF1_SEPARATOR`, as
+/// little-endian UCS-2, terminated by `PSF1_TERMINATOR`.
+fn write_psf1_unicode_table(unicode_table: &UnicodeTable) -> Vec<u8> {
+    let mut table: Vec<u8> = vec![];
+    let mut push = |value: u16| table.extend(value.to_le_bytes());
+    for graphemes in unicode_table.data.iter() {
+        // graphemes are sorted from fewest to most codepoints, so the single codepoints come first.
+        for grapheme in graphemes.iter() {
+            if grapheme.chars().count() > 1 {
+                push(PSF1_SEPARATOR);
+            }
+            grapheme.chars().for_each(|c| push(u32::from(c) as u16));
+        }
+        push(PSF1_TERMINATOR);
+    }
+    return table;
+}
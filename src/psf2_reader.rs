@@ -0,0 +1,236 @@
+use crate::errors::Psf2ReadError;
+use crate::glyph::Glyph;
+use crate::psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header};
+use crate::unicode_table::{UnicodeTable, UnicodeTableMeta};
+
+const PSF2_MAGIC_BYTES: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_MIN_HEADER_SIZE: usize = 32;
+/// The only PSF2 version defined so far.
+const PSF2_SUPPORTED_VERSION: u32 = 0;
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+/// Starts a multi-codepoint sequence in a PSF2 Unicode table entry.
+const PSF2_SEPARATOR: u8 = 0xfe;
+/// Terminates a PSF2 Unicode table entry.
+const PSF2_TERMINATOR: u8 = 0xff;
+
+/// The fields of a PSF2 header, as stored in the file.
+pub struct RawPsf2Header {
+    /// Whether the file starts with the PSF2 magic bytes.
+    pub magic_valid: bool,
+    pub version: u32,
+    /// The offset in bytes of the glyph bitmaps from the start of the file.
+    pub header_size: u32,
+    pub flags: u32,
+    pub glyph_count: u32,
+    /// The number of bytes used to store each glyph.
+    pub glyph_size: u32,
+    pub height: u32,
+    pub width: u32,
+}
+
+impl RawPsf2Header {
+    /// Whether the flags say that a Unicode table follows the glyphs.
+    pub fn unicode_table_exists(&self) -> bool {
+        return self.flags & PSF2_HAS_UNICODE_TABLE != 0;
+    }
+}
+
+/// Reads the 32-byte header at the start of a PSF2 font file, without checking the magic bytes
+/// or reading any glyphs.
+pub fn read_psf2_header(bytes: &[u8]) -> Result<RawPsf2Header, Psf2ReadError> {
+    if bytes.len() < PSF2_MIN_HEADER_SIZE {
+        return Err(Psf2ReadError::TooShort{length: bytes.len(), expected_length: PSF2_MIN_HEADER_SIZE});
+    }
+    let field = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+    return Ok(RawPsf2Header{
+        magic_valid: bytes[0..4] == PSF2_MAGIC_BYTES,
+        version: field(4),
+        header_size: field(8),
+        flags: field(12),
+        glyph_count: field(16),
+        glyph_size: field(20),
+        height: field(24),
+        width: field(28),
+    });
+}
+
+/// The result of one structural check on a PSF2 file, from `verify_psf2`.
+pub struct Psf2Check {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What was found, e.g. the expected and actual values.
+    pub detail: String,
+}
+
+/// Checks the structure of a PSF2 file without decoding its glyphs: the magic bytes, version, and
+/// header size, that the glyph size matches the glyph dimensions, that the file is exactly as long
+/// as its header says, and that the Unicode table (if any) has one terminated entry per glyph.
+/// Returns an error only if the file is too short to have a header.
+pub fn verify_psf2(bytes: &[u8]) -> Result<Vec<Psf2Check>, Psf2ReadError> {
+    let header = read_psf2_header(bytes)?;
+    let check = |name: &'static str, passed: bool, detail: String| Psf2Check{name, passed, detail};
+    let mut checks = vec![
+        check("Magic", header.magic_valid, format!("{:02x?}", &bytes[0..4])),
+        check("Version", header.version == PSF2_SUPPORTED_VERSION, 
+            format!("version {} (expected {})", header.version, PSF2_SUPPORTED_VERSION)),
+        check("Header size", header.header_size as usize >= PSF2_MIN_HEADER_SIZE, 
+            format!("{} bytes (expected at least {})", header.header_size, PSF2_MIN_HEADER_SIZE)),
+    ];
+    let expected_glyph_size = header.width.div_ceil(8) as u64 * header.height as u64;
+    checks.push(check("Glyph size", header.glyph_size as u64 == expected_glyph_size, 
+        format!("{} bytes (expected {} for {} x {} px glyphs)", header.glyph_size, expected_glyph_size, header.height, header.width)));
+
+    let glyphs_end = header.header_size as u64 + header.glyph_count as u64 * header.glyph_size as u64;
+    let length = bytes.len() as u64;
+    if !header.unicode_table_exists() {
+        checks.push(check("File length", length == glyphs_end, 
+            format!("{} bytes (expected {} for the header and glyphs)", length, glyphs_end)));
+        return Ok(checks);
+    }
+    checks.push(check("File length", length >= glyphs_end, 
+        format!("{} bytes (expected at least {} for the header and glyphs, plus the Unicode table)", length, glyphs_end)));
+    let table = bytes.get(glyphs_end as usize..).unwrap_or_default();
+    let terminator_count = table.iter().filter(|b| **b == PSF2_TERMINATOR).count();
+    checks.push(check("Unicode table entries", terminator_count == header.glyph_count as usize, 
+        format!("{} terminators (expected one per glyph, {})", terminator_count, header.glyph_count)));
+    checks.push(check("Unicode table end", table.last() == Some(&PSF2_TERMINATOR), 
+        format!("{} bytes after the last terminator", table.iter().rev().take_while(|b| **b != PSF2_TERMINATOR).count())));
+    return Ok(checks);
+}
+
+impl Psf2Font {
+    /// Reads a PSF2 font from the contents of a font file, checking the magic bytes, version,
+    /// header size, and that the glyph size matches the glyph dimensions. Returns an error rather
+    /// than panicking if the file is shorter than its header says.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Psf2Font, Psf2ReadError> {
+        let raw_header = read_psf2_header(bytes)?;
+        if !raw_header.magic_valid {
+            return Err(Psf2ReadError::BadMagic);
+        }
+        if raw_header.version != PSF2_SUPPORTED_VERSION {
+            return Err(Psf2ReadError::UnsupportedVersion{version: raw_header.version});
+        }
+        let header_size = raw_header.header_size as usize;
+        if header_size < PSF2_MIN_HEADER_SIZE {
+            return Err(Psf2ReadError::BadHeaderSize{header_size});
+        }
+        let expected_glyph_size = raw_header.width.div_ceil(8) as u64 * raw_header.height as u64;
+        if raw_header.glyph_size as u64 != expected_glyph_size || expected_glyph_size == 0 {
+            return Err(Psf2ReadError::BadGlyphSize{glyph_size: raw_header.glyph_size, expected_glyph_size, 
+                height: raw_header.height, width: raw_header.width});
+        }
+        let header = Psf2Header{
+            unicode_table_exists: raw_header.unicode_table_exists(),
+            glyph_count: raw_header.glyph_count,
+            glyph_size: raw_header.glyph_size,
+            glyph_height: raw_header.height,
+            glyph_width: raw_header.width,
+        };
+
+        // a glyph count and size too large to add up can only belong to a truncated file.
+        let glyphs_end = (header.glyph_count as usize).checked_mul(header.glyph_size as usize)
+            .and_then(|glyphs_length| glyphs_length.checked_add(header_size))
+            .unwrap_or(usize::MAX);
+        if bytes.len() < glyphs_end {
+            return Err(Psf2ReadError::TooShort{length: bytes.len(), expected_length: glyphs_end});
+        }
+
+        let unicode_table = match header.unicode_table_exists {
+            true => Some(read_unicode_table(&bytes[glyphs_end..], header.glyph_count)?),
+            false => None,
+        };
+
+        let glyphs: Vec<Glyph> = bytes[header_size..glyphs_end]
+            .chunks_exact(header.glyph_size as usize)
+            .take(header.glyph_count as usize)
+            .enumerate()
+            .map(|(i, data)| Glyph{
+                height: header.glyph_height,
+                width: header.glyph_width,
+                data: data.to_vec(),
+                grapheme: match &unicode_table {
+                    Some(uc) => uc.data[i].first().cloned().unwrap_or_default(),
+                    None => char::from_u32(i as u32).map(String::from).unwrap_or_default(),
+                },
+                bearing_x: None,
+                bearing_y: None,
+                x_offset: None,
+                y_offset: None,
+                // PSF2 does not record the size the glyphs were rendered at.
+                ppem: header.glyph_height,
+            })
+            .collect();
+        let glyphs = Psf2GlyphSet::from_glyphs(glyphs, false)?;
+
+        return Ok(Psf2Font{header, glyphs, unicode_table});
+    }
+}
+
+/// Reads `glyph_count` PSF2 Unicode table entries. Each entry lists single codepoints, then
+/// multi-codepoint sequences each preceded by `PSF2_SEPARATOR`, terminated by `PSF2_TERMINATOR`.
+pub fn read_unicode_table(bytes: &[u8], glyph_count: u32) -> Result<UnicodeTable, Psf2ReadError> {
+    let mut entries = bytes.split(|b| *b == PSF2_TERMINATOR);
+    let mut data: Vec<Vec<String>> = vec![];
+    for glyph_index in 0..glyph_count as usize {
+        let entry = entries.next().ok_or(Psf2ReadError::TruncatedUnicodeTable{glyph_index})?;
+        let mut segments = entry.split(|b| *b == PSF2_SEPARATOR);
+        let decode = |segment: &[u8]| String::from_utf8(segment.to_vec())
+            .map_err(|_| Psf2ReadError::InvalidUnicodeTable{glyph_index});
+
+        // the first segment is a run of single-codepoint graphemes; every later segment is one
+        // multi-codepoint grapheme.
+        let mut graphemes: Vec<String> = decode(segments.next().unwrap_or_default())?
+            .chars().map(String::from).collect();
+        for segment in segments {
+            graphemes.push(decode(segment)?);
+        }
+        data.push(graphemes);
+    }
+    return Ok(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0});
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_bytes(glyph_count: u32, glyph_size: u32, height: u32, width: u32) -> Vec<u8> {
+        let header = Psf2Header{
+            unicode_table_exists: false, glyph_count, glyph_size, glyph_height: height, glyph_width: width,
+        };
+        return header.write().to_vec();
+    }
+
+    #[test]
+    fn reads_glyphs_of_matching_size() {
+        let mut bytes = header_bytes(2, 16, 8, 16);
+        bytes.extend([0xaa; 32]);
+        let font = Psf2Font::from_bytes(&bytes).unwrap();
+        assert_eq!(font.glyphs.glyphs().len(), 2);
+        assert!(font.glyphs.glyphs()[1].get_pixel(14, 7));
+        assert!(!font.glyphs.glyphs()[1].get_pixel(15, 7));
+    }
+
+    #[test]
+    fn rejects_glyph_size_smaller_than_dimensions() {
+        let mut bytes = header_bytes(1, 1, 8, 16);
+        bytes.push(0xff);
+        assert!(matches!(Psf2Font::from_bytes(&bytes), 
+            Err(Psf2ReadError::BadGlyphSize{glyph_size: 1, expected_glyph_size: 16, ..})));
+    }
+
+    #[test]
+    fn rejects_huge_glyph_count_as_too_short() {
+        // the largest glyph count and glyph size a header can claim, with no glyphs after it.
+        let bytes = header_bytes(u32::MAX, u32::MAX, u32::MAX, 8);
+        let length = bytes.len();
+        assert!(matches!(Psf2Font::from_bytes(&bytes), Err(Psf2ReadError::TooShort{length: l, ..}) if l == length));
+    }
+
+    #[test]
+    fn rejects_zero_glyph_size() {
+        let bytes = header_bytes(4, 0, 8, 8);
+        assert!(matches!(Psf2Font::from_bytes(&bytes), Err(Psf2ReadError::BadGlyphSize{glyph_size: 0, ..})));
+        let bytes = header_bytes(4, 0, 0, 0);
+        assert!(matches!(Psf2Font::from_bytes(&bytes), Err(Psf2ReadError::BadGlyphSize{glyph_size: 0, ..})));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::errors::Psf2TextError;
+use crate::glyph::Glyph;
+use crate::psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header};
+use crate::unicode_table::{parse_codepoint, UnicodeTable, UnicodeTableMeta};
+
+/// The first line of every debug text file.
+const DEBUG_TEXT_MAGIC: &str = "otf2psf debug text";
+
+impl Psf2Font {
+    /// Describes the font in an editable text format: the header fields, then each glyph as a
+    /// `glyph <index>:` line listing its Unicode table entry in charset syntax, followed by its
+    /// bitmap as ASCII art. `from_debug_text` reads the format back into an identical font, as
+    /// long as the padding bits at the end of each glyph row are blank.
+    pub fn to_debug_text(&self) -> String {
+        let mut text = format!("{}\nheight {}\nwidth {}\nunicode-table {}\n", DEBUG_TEXT_MAGIC, 
+            self.header.glyph_height, self.header.glyph_width, 
+            if self.header.unicode_table_exists {"yes"} else {"no"});
+        for (i, glyph) in self.glyphs.glyphs().iter().enumerate() {
+            let graphemes = self.unicode_table.as_ref().and_then(|uc| uc.data.get(i))
+                .map(|group| group.iter()
+                    .map(|g| g.chars().map(|c| format!("U+{:04X}", u32::from(c))).collect::<Vec<_>>().join(" "))
+                    .collect::<Vec<_>>()
+                    .join(", "))
+                .unwrap_or_default();
+            text.push_str(&match graphemes.is_empty() {
+                true => format!("\nglyph {}:\n", i),
+                false => format!("\nglyph {}: {}\n", i, graphemes),
+            });
+            text.push_str(&glyph.to_ascii_art());
+            text.push('\n');
+        }
+        return text;
+    }
+
+    /// Reads a font written by `to_debug_text`. Blank lines are ignored.
+    pub fn from_debug_text(text: &str) -> Result<Self, Psf2TextError> {
+        let mut lines = text.lines().enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty());
+        let invalid = |(line, text): (usize, &str), expected: &'static str| 
+            Psf2TextError::InvalidLine{line, text: text.to_string(), expected};
+
+        match lines.next() {
+            Some((_, DEBUG_TEXT_MAGIC)) => (),
+            Some(line) => return Err(invalid(line, DEBUG_TEXT_MAGIC)),
+            None => return Err(Psf2TextError::MissingField{name: DEBUG_TEXT_MAGIC}),
+        }
+        let mut field = |name: &'static str| -> Result<String, Psf2TextError> {
+            let line = lines.next().ok_or(Psf2TextError::MissingField{name})?;
+            return line.1.strip_prefix(name).and_then(|value| value.strip_prefix(' '))
+                .map(String::from)
+                .ok_or(invalid(line, name));
+        };
+        let height = field("height")?.parse::<u32>().map_err(|_| Psf2TextError::MissingField{name: "height"})?;
+        let width = field("width")?.parse::<u32>().map_err(|_| Psf2TextError::MissingField{name: "width"})?;
+        let unicode_table_exists = match field("unicode-table")?.as_str() {
+            "yes" => true,
+            "no" => false,
+            _ => return Err(Psf2TextError::MissingField{name: "unicode-table"}),
+        };
+
+        let mut glyphs: Vec<Glyph> = vec![];
+        let mut data: Vec<Vec<String>> = vec![];
+        while let Some(line) = lines.next() {
+            let graphemes = line.1.strip_prefix(&format!("glyph {}:", glyphs.len()))
+                .ok_or(invalid(line, "glyph <index>:"))?;
+            let group = graphemes.split(',')
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+                .map(|g| g.split_whitespace()
+                    .map(|codepoint| parse_codepoint(codepoint.trim_start_matches(['U', 'u']).trim_start_matches('+')))
+                    .collect::<Result<String, _>>())
+                .collect::<Result<Vec<String>, _>>()?;
+
+            let mut rows: Vec<&str> = vec![];
+            for _ in 0..height {
+                let row = lines.next().ok_or(Psf2TextError::MissingField{name: "glyph row"})?;
+                if row.1.chars().count() != width as usize {
+                    return Err(invalid(row, "a row of `#` and `.` as wide as the glyph"));
+                }
+                rows.push(row.1);
+            }
+            let grapheme = match unicode_table_exists {
+                true => group.first().cloned().unwrap_or_default(),
+                false => char::from_u32(glyphs.len() as u32).map(String::from).unwrap_or_default(),
+            };
+            let glyph = Glyph::from_ascii_art(&rows, grapheme, height)
+                .ok_or(Psf2TextError::InvalidGlyph{glyph_index: glyphs.len()})?;
+            glyphs.push(glyph);
+            data.push(group);
+        }
+
+        let glyphs = Psf2GlyphSet::from_glyphs(glyphs, false)?;
+        let header = Psf2Header{
+            unicode_table_exists,
+            glyph_count: glyphs.glyphs().len() as u32,
+            glyph_size: width.div_ceil(8) * height,
+            glyph_height: height,
+            glyph_width: width,
+        };
+        let unicode_table = unicode_table_exists.then_some(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0});
+        return Ok(Psf2Font{header, glyphs, unicode_table});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font(unicode_table: Option<UnicodeTable>) -> Psf2Font {
+        let glyph = |rows: &[&str], grapheme: &str| Glyph::from_ascii_art(rows, grapheme.to_string(), 3).unwrap();
+        let glyphs = Psf2GlyphSet::from_glyphs(vec![
+            glyph(&[".#.", "#.#", "###"], "A"),
+            glyph(&["...", "...", "..."], ""),
+            glyph(&["#..", ".#.", "..#"], "e\u{301}"),
+        ], false).unwrap();
+        let header = Psf2Header{
+            unicode_table_exists: unicode_table.is_some(),
+            glyph_count: 3,
+            glyph_size: 3,
+            glyph_height: 3,
+            glyph_width: 3,
+        };
+        return Psf2Font{header, glyphs, unicode_table};
+    }
+
+    #[test]
+    fn debug_text_round_trips_byte_identical() {
+        let data = vec![
+            vec!["A".to_string(), "\u{391}".to_string()],
+            vec![],
+            vec!["e\u{301}".to_string(), "\u{e9}".to_string()],
+        ];
+        for font in [font(Some(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0})), font(None)] {
+            let text = font.to_debug_text();
+            let read_back = Psf2Font::from_debug_text(&text).unwrap();
+            assert_eq!(read_back.write(), font.write());
+            assert_eq!(read_back.to_debug_text(), text);
+        }
+    }
+}
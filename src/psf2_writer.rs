@@ -1,13 +1,111 @@
-use crate::glyph::Glyph;
-use crate::ttf_parser::TtfParser;
+use crate::char_info::char_name;
+use crate::glyph::{Glyph, HAnchor, VAnchor};
+use crate::profile::GlyphTimings;
+use crate::ttf_parser::{is_blank_space, TtfParser};
 use crate::errors::GlyphSetError;
 use crate::unicode_table::UnicodeTable;
 
+use clap::ValueEnum;
+use itertools::Itertools;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
 
 const PSF2_MAGIC_BYTES: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
 const PSF2_VERSION: [u8; 4] = [0x0, 0x0, 0x0, 0x0];
 const PSF2_HEADER_SIZE: [u8; 4] = 32_u32.to_le_bytes();
 
+/// Where `--pad` places each smaller glyph in the padded cell, as `<horizontal>-<vertical>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PadAnchor {
+    /// At the top left, with blank space added to the right and below.
+    LeftTop,
+    /// Centered horizontally, at the top.
+    CenterTop,
+    /// Centered both ways. Odd differences round left and up.
+    Center,
+    /// Centered horizontally, at the bottom.
+    CenterBottom,
+}
+
+impl PadAnchor {
+    pub fn anchors(self) -> (HAnchor, VAnchor) {
+        return match self {
+            PadAnchor::LeftTop => (HAnchor::Left, VAnchor::Top),
+            PadAnchor::CenterTop => (HAnchor::Center, VAnchor::Top),
+            PadAnchor::Center => (HAnchor::Center, VAnchor::Center),
+            PadAnchor::CenterBottom => (HAnchor::Center, VAnchor::Bottom),
+        };
+    }
+}
+
+/// Which grapheme of a Unicode table entry is rendered as the entry's glyph. Every grapheme in the
+/// entry is mapped to the glyph either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderGrapheme {
+    /// The grapheme with the fewest codepoints, or the first listed among equally short ones.
+    First,
+    /// The grapheme with the most codepoints, or the first listed among equally long ones. Useful
+    /// when a multi-codepoint sequence is the intended appearance.
+    Longest,
+    /// The `n`th grapheme, counting from 1 in order of codepoint count, or the first if the entry
+    /// has fewer than `n`.
+    Specific(usize),
+}
+
+impl RenderGrapheme {
+    /// Picks the grapheme to render from a Unicode table entry, whose graphemes are sorted from
+    /// fewest to most codepoints. Returns `None` for an empty entry.
+    pub fn pick(self, graphemes: &[String]) -> Option<&String> {
+        return match self {
+            RenderGrapheme::First => graphemes.first(),
+            RenderGrapheme::Longest => graphemes.iter().min_by_key(|g| std::cmp::Reverse(g.chars().count())),
+            RenderGrapheme::Specific(n) => graphemes.get(n.saturating_sub(1)).or(graphemes.first()),
+        };
+    }
+}
+
+/// Writes the choice as `first`, `longest`, or `specific:<n>`, as `--render-grapheme` takes it.
+impl Display for RenderGrapheme {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        return match self {
+            RenderGrapheme::First => write!(f, "first"),
+            RenderGrapheme::Longest => write!(f, "longest"),
+            RenderGrapheme::Specific(n) => write!(f, "specific:{}", n),
+        };
+    }
+}
+
+/// The number of graphemes `WidthHistogram` lists for each uncommon width.
+const WIDTH_HISTOGRAM_LISTED_GRAPHEMES: usize = 10;
+
+/// The glyphs of a set at each width, from `Psf2GlyphSet::analyze_widths`.
+pub struct WidthHistogram {
+    /// The graphemes of the glyphs at each width.
+    pub widths: BTreeMap<u32, Vec<String>>,
+    pub glyph_count: usize,
+}
+
+impl Display for WidthHistogram {
+    /// Lists each width from most to least common, with the graphemes drawn at every width but
+    /// the most common one.
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let by_count = self.widths.iter().sorted_by_key(|(w, graphemes)| (std::cmp::Reverse(graphemes.len()), **w));
+        for (i, (width, graphemes)) in by_count.enumerate() {
+            write!(f, "{} glyphs ({:.1}%) are {} px wide", 
+                graphemes.len(), graphemes.len() as f64 * 100.0 / self.glyph_count as f64, width)?;
+            if i > 0 {
+                write!(f, ": {}", graphemes.iter().take(WIDTH_HISTOGRAM_LISTED_GRAPHEMES).map(|g| format!("{:?}", g)).join(", "))?;
+                if graphemes.len() > WIDTH_HISTOGRAM_LISTED_GRAPHEMES {
+                    write!(f, ", and {} more", graphemes.len() - WIDTH_HISTOGRAM_LISTED_GRAPHEMES)?;
+                }
+            }
+            writeln!(f, ".")?;
+        }
+        return Ok(());
+    }
+}
+
 /// Header information for a PSF2 font file.
 pub struct Psf2Header {
     /// Specifies whether a Unicode mapping table is included for this font. If false, glyphs will
@@ -26,7 +124,7 @@ pub struct Psf2Header {
 
 impl Psf2Header {
     /// Writes the PSF2 header to an array of bytes.
-    pub fn write(self) -> [u8; 32] {
+    pub fn write(&self) -> [u8; 32] {
         let flags: [u8;4] = (self.unicode_table_exists as u32).to_le_bytes();
         let mut header = [0u8; 32];
         header[ 0.. 4].clone_from_slice(&PSF2_MAGIC_BYTES);
@@ -57,50 +155,165 @@ pub struct Psf2GlyphSet {
 }
 
 impl Psf2GlyphSet {
-    pub fn new_with_unicode_table(ttf_parser: TtfParser, unicode_table: &UnicodeTable, pad: bool) 
-        -> Result<Self, GlyphSetError> {
+    /// Renders one glyph per Unicode table entry, using the grapheme `render_grapheme` picks from
+    /// the entry. Unless `allow_missing` is set, first returns an error listing every picked
+    /// grapheme that neither the font nor a fallback defines, rather than rendering `.notdef` for
+    /// it. The render time of each glyph is recorded in `timings`.
+    pub fn render_with_unicode_table(ttf_parser: &TtfParser, unicode_table: &UnicodeTable, 
+        render_grapheme: RenderGrapheme, allow_missing: bool, timings: &mut GlyphTimings) 
+        -> Result<Vec<Glyph>, GlyphSetError> {
+        if !allow_missing {
+            // spaces are always rendered blank, so they are never missing.
+            let graphemes: Vec<String> = unicode_table.data.iter()
+                .filter_map(|entry| render_grapheme.pick(entry))
+                .filter(|g| !g.chars().all(|c| is_blank_space(c) || ttf_parser.is_defined_in_chain(c)))
+                .cloned()
+                .collect();
+            if !graphemes.is_empty() {
+                return Err(GlyphSetError::UndefinedGraphemes{graphemes});
+            }
+        }
         let mut glyph_set: Vec<Glyph> = vec![];
         for equivalent_graphemes_list in unicode_table.data.iter() {
             // select a "reference grapheme" to rasterize and use as a symbol for a set of
             // equivalent graphemes.
-            let reference_grapheme = &equivalent_graphemes_list[0];
-            glyph_set.push(ttf_parser.render_string(reference_grapheme)?);
+            let Some(reference_grapheme) = render_grapheme.pick(equivalent_graphemes_list) else {
+                // a gap left between pinned glyph indices.
+                glyph_set.push(Glyph{grapheme: String::new(), ..ttf_parser.render_blank(' ')?});
+                continue;
+            };
+            glyph_set.push(timings.time(
+                || match reference_grapheme.chars().exactly_one() {
+                    Ok(c) => char_name(c),
+                    Err(_) => format!("{:?}", reference_grapheme),
+                },
+                || ttf_parser.render_string(reference_grapheme),
+            )?);
         }
+        return Ok(glyph_set);
+    }
+
+    /// Renders glyphs for Unicode codepoints `U+0000` through `U+(glyph_count - 1)`. Surrogate
+    /// codepoints, which are not characters, are always blank, so that later glyphs stay at the
+    /// index of their codepoint. The render time of each glyph is recorded in `timings`.
+    pub fn render(ttf_parser: &TtfParser, glyph_count: u32, timings: &mut GlyphTimings) 
+        -> Result<Vec<Glyph>, GlyphSetError> {
+        return Ok((0..(glyph_count)).map(
+            |i| {
+                let Some(character) = char::from_u32(i) else {
+                    return ttf_parser.render_blank(' ').map(|g| Glyph{grapheme: String::new(), ..g});
+                };
+                timings.time(|| char_name(character), || ttf_parser.render_char(character))
+            }
+        ).collect::<Result<Vec<Glyph>, _>>()?);
+    }
 
+    /// Renders glyph ids `0` through `glyph_count - 1` directly, ignoring the font's cmap. The
+    /// render time of each glyph is recorded in `timings`.
+    pub fn render_by_glyph_id(ttf_parser: &TtfParser, glyph_count: u32, timings: &mut GlyphTimings) 
+        -> Result<Vec<Glyph>, GlyphSetError> {
+        return Ok((0..glyph_count).map(
+            |i| timings.time(|| format!("glyph id {}", i), || ttf_parser.render_glyph_id(i as u16))
+        ).collect::<Result<Vec<Glyph>, _>>()?);
+    }
+
+    /// Merges glyphs whose bitmaps are identical into the first of them, and merges their Unicode
+    /// table entries to match, so that glyph `i` still matches entry `i`. Glyphs are grouped by
+    /// `Glyph::bitmap_digest`, then compared in full. Glyphs within the table's `pinned_len` are
+    /// never merged away, so pinned glyphs keep their indices, and glyphs with empty entries (the
+    /// blank gaps between pins) are left as they are. Returns the remaining glyphs.
+    pub fn dedup(glyphs: Vec<Glyph>, unicode_table: &mut UnicodeTable) -> Vec<Glyph> {
+        let mut kept: Vec<Glyph> = vec![];
+        let mut data: Vec<Vec<String>> = vec![];
+        // the indices into `kept` of the glyphs with each digest.
+        let mut by_digest: HashMap<u64, Vec<usize>> = HashMap::new();
+        let pinned_len = unicode_table.pinned_len;
+        for (i, (glyph, graphemes)) in glyphs.into_iter().zip(std::mem::take(&mut unicode_table.data)).enumerate() {
+            let candidates = by_digest.entry(glyph.bitmap_digest()).or_default();
+            let same_bitmap = |k: &Glyph| (k.height, k.width, &k.data) == (glyph.height, glyph.width, &glyph.data);
+            let existing = match i < pinned_len || graphemes.is_empty() {
+                true => None,
+                false => candidates.iter().find(|k| same_bitmap(&kept[**k])),
+            };
+            match existing {
+                Some(k) => data[*k].extend(graphemes),
+                None => {
+                    if !graphemes.is_empty() {
+                        candidates.push(kept.len());
+                    }
+                    kept.push(glyph);
+                    data.push(graphemes);
+                }
+            }
+        }
+        unicode_table.data = data;
+        return kept;
+    }
+
+    /// Builds a glyph set from rendered glyphs, checking that they all share the same dimensions.
+    /// If `pad` is set, glyphs are first padded to the size of the largest glyph.
+    pub fn from_glyphs(glyphs: Vec<Glyph>, pad: bool) -> Result<Self, GlyphSetError> {
         return match pad {
-            true => Self::from_vec_of_glyphs_pad(glyph_set),
-            false => Self::from_vec_of_glyphs_strict(glyph_set),
+            true => Self::from_vec_of_glyphs_pad(glyphs, PadAnchor::LeftTop),
+            false => Self::from_vec_of_glyphs_strict(glyphs),
         }
-        
     }
 
-    pub fn new(ttf_parser: TtfParser, glyph_count: u32, pad: bool) -> Result<Self, GlyphSetError> {
-        let glyph_set: Vec<Glyph> = (0..(glyph_count)).map(
-            |i|
-            ttf_parser.render_char(char::from_u32(i).expect("Invalid Unicode codepoint while generating glyph set"))
-        ).collect();
+    /// Builds a glyph set from rendered glyphs, padding each glyph to the size of the largest
+    /// glyph, placed in the padded cell according to `anchor`.
+    pub fn from_glyphs_pad_anchored(glyphs: Vec<Glyph>, anchor: PadAnchor) -> Result<Self, GlyphSetError> {
+        return Self::from_vec_of_glyphs_pad(glyphs, anchor);
+    }
 
-        return match pad {
-            true => Self::from_vec_of_glyphs_pad(glyph_set),
-            false => Self::from_vec_of_glyphs_strict(glyph_set),
+    /// Groups glyphs by width, to show which glyphs keep a set of glyphs from being monospace.
+    pub fn analyze_widths(glyphs: &[Glyph]) -> WidthHistogram {
+        let mut widths: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for g in glyphs.iter() {
+            widths.entry(g.width).or_default().push(g.grapheme.clone());
         }
+        return WidthHistogram{widths, glyph_count: glyphs.len()};
+    }
+
+    /// Builds a glyph set from rendered glyphs, padding or cropping the glyphs whose dimensions
+    /// differ from the most common dimensions in the set. Each adjusted glyph is reported on
+    /// stderr. Ties between equally common dimensions go to the dimensions seen first.
+    pub fn from_glyphs_pad_to_mode(glyphs: Vec<Glyph>) -> Result<Self, GlyphSetError> {
+        let dimensions: Vec<(u32, u32)> = glyphs.iter().map(|g| (g.height, g.width)).collect();
+        let counts = dimensions.iter().counts();
+        let Some((height, width)) = dimensions.iter().copied()
+            .rev() // `max_by_key` keeps the last maximum, so search from the end.
+            .max_by_key(|d| counts[d]) else {
+            return Self::from_vec_of_glyphs_strict(glyphs);
+        };
+
+        let resized_glyphs: Vec<Glyph> = glyphs.into_iter().enumerate()
+            .map(|(i, g)| {
+                if (g.height, g.width) == (height, width) {
+                    return g;
+                }
+                log::warn!("Resized glyph {} ({:?}) from {} x {} px to {} x {} px.", i, g.grapheme, g.height, g.width, height, width);
+                g.resize_canvas(height, width, HAnchor::Left, VAnchor::Top)
+            })
+            .collect();
+        return Self::from_vec_of_glyphs_strict(resized_glyphs);
     }
 
-    fn from_vec_of_glyphs_pad(glyphs: Vec<Glyph>) -> Result<Self, GlyphSetError> {
+    fn from_vec_of_glyphs_pad(glyphs: Vec<Glyph>, anchor: PadAnchor) -> Result<Self, GlyphSetError> {
         let mut max_height: u32 = 0;
         let mut max_width: u32 = 0;
         let mut max_length: u32 = 0;
 
         for g in glyphs.iter() {
-            max_height = std::cmp::max(u32::from(g.height), max_height);
-            max_width = std::cmp::max(u32::from(g.width), max_width);
+            max_height = std::cmp::max(g.height, max_height);
+            max_width = std::cmp::max(g.width, max_width);
             max_length = std::cmp::max(u32::try_from(g.data.len()).unwrap(), max_length);
         }
 
         let mut padded_glyphs: Vec<Glyph> = vec![];
+        let (h_anchor, v_anchor) = anchor.anchors();
 
         for g in glyphs.into_iter() {
-            let padded = g.pad(max_height, max_width)?;
+            let padded = g.pad_anchored(max_height, max_width, h_anchor, v_anchor)?;
             padded_glyphs.push(padded);
         }
 
@@ -108,7 +321,10 @@ impl Psf2GlyphSet {
         return Self::from_vec_of_glyphs_strict(padded_glyphs);
     }
 
-    fn from_vec_of_glyphs_strict(glyphs: Vec<Glyph>) -> Result<Self, GlyphSetError> {
+    fn from_vec_of_glyphs_strict(mut glyphs: Vec<Glyph>) -> Result<Self, GlyphSetError> {
+        // consoles may draw stray bits left past the glyph width by bitmap transforms.
+        glyphs.iter_mut().for_each(Glyph::clear_padding_bits);
+
         // check that all heights/widths/lengths are equal.
         let height: u32;
         let width: u32;
@@ -127,8 +343,8 @@ impl Psf2GlyphSet {
                 (height, width, length) = (f.height, f.width, f.data.len() as u32);
 
                 for g in glyph_set_iter {
-                    if u32::from(g.height) != height 
-                        || u32::from(g.width) != width {
+                    if g.height != height 
+                        || g.width != width {
                         return Err(GlyphSetError::InconsistentDimensions{
                             height: g.height, 
                             width: g.width, 
@@ -148,8 +364,28 @@ impl Psf2GlyphSet {
 
     }
 
-    pub fn write(self) -> Vec<u8> {
-        return self.glyphs.into_iter().map(|g| g.data).flatten().collect();
+    /// Grows every glyph's cell by `right` blank columns and `bottom` blank rows.
+    pub fn with_gap(self, right: u32, bottom: u32) -> Result<Self, GlyphSetError> {
+        let (height, width) = (self.height + bottom, self.width + right);
+        let mut gapped_glyphs: Vec<Glyph> = vec![];
+        for g in self.glyphs.into_iter() {
+            gapped_glyphs.push(g.pad(height, width)?);
+        }
+        return Self::from_vec_of_glyphs_strict(gapped_glyphs);
+    }
+
+    /// The glyphs in the set, in order.
+    pub fn glyphs(&self) -> &[Glyph] {
+        return &self.glyphs;
+    }
+
+    /// Consumes the glyph set, returning its glyphs in order.
+    pub fn into_glyphs(self) -> Vec<Glyph> {
+        return self.glyphs;
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        return self.glyphs.iter().flat_map(|g| g.data.iter().copied()).collect();
     }
 }
 
@@ -161,19 +397,144 @@ pub struct Psf2Font {
 }
 
 impl Psf2Font {
-    pub fn write(self) -> Vec<u8> {
+    pub fn write(&self) -> Vec<u8> {
         let mut font: Vec<u8> = self.header.write().to_vec();
-        eprintln!("Font header length: {}", font.len());
+        log::debug!("Font header length: {}", font.len());
         let glyphs_data = self.glyphs.write();
-        eprintln!("Glyph set length: {}", glyphs_data.len());
+        log::debug!("Glyph set length: {}", glyphs_data.len());
         //font.extend(self.glyphs.write());
         font.extend(glyphs_data);
-        if let Some(uc) = self.unicode_table {
+        if let Some(uc) = &self.unicode_table {
             let uc_table = uc.write();
-            eprintln!("Unicode table length: {}", uc_table.len());
+            log::debug!("Unicode table length: {}", uc_table.len());
             font.extend(uc_table);
         };
-        eprintln!("Total font length: {}", font.len());
+        log::debug!("Total font length: {}", font.len());
         return font;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Just past the first surrogate codepoints, U+D800 and U+D801.
+    const GLYPH_COUNT_PAST_SURROGATES: u32 = 0xd802;
+
+    fn demo_font(height: u32) -> TtfParser {
+        return TtfParser::from_bytes(include_bytes!("../fixtures/demo.ttf").to_vec(), height).unwrap();
+    }
+
+    /// The demo font with its `.notdef` entry in `loca`, the table at byte 300, pointed at the 
+    /// outline of 'A', so that it draws a different missing-glyph box in the same cell.
+    fn demo_font_with_other_notdef(height: u32) -> TtfParser {
+        let mut font_data = include_bytes!("../fixtures/demo.ttf").to_vec();
+        font_data.copy_within(302..304, 300);
+        return TtfParser::from_bytes(font_data, height).unwrap();
+    }
+
+    #[test]
+    fn blank_missing_is_identical_across_fonts() {
+        let (mut demo, mut other) = (demo_font(16), demo_font_with_other_notdef(16));
+        let render = |ttf_parser: &TtfParser| Psf2GlyphSet::render(ttf_parser, 0x80, &mut GlyphTimings::new(0)).unwrap();
+        let undefined = |glyphs: &[Glyph]| glyphs.iter().enumerate().filter(|(i, _)| *i != 0x41)
+            .map(|(_, g)| g.data.clone()).collect::<Vec<_>>();
+        assert_ne!(undefined(&render(&demo)), undefined(&render(&other)));
+        demo.set_blank_missing(true);
+        other.set_blank_missing(true);
+        assert_eq!(undefined(&render(&demo)), undefined(&render(&other)));
+    }
+
+    #[test]
+    fn identity_table_renders_same_glyphs_as_no_table() {
+        let ttf_parser = demo_font(8);
+        let table = UnicodeTable::identity(GLYPH_COUNT_PAST_SURROGATES);
+        let with_table = Psf2GlyphSet::render_with_unicode_table(&ttf_parser, &table, RenderGrapheme::First, 
+            true, &mut GlyphTimings::new(0)).unwrap();
+        let without_table = Psf2GlyphSet::render(&ttf_parser, GLYPH_COUNT_PAST_SURROGATES, 
+            &mut GlyphTimings::new(0)).unwrap();
+        assert_eq!(with_table.len(), without_table.len());
+        for (i, (a, b)) in with_table.iter().zip(without_table.iter()).enumerate() {
+            assert_eq!(a.data, b.data, "glyph {:#x} differs", i);
+        }
+    }
+
+    #[test]
+    fn surrogates_render_as_blank_placeholders() {
+        let ttf_parser = demo_font(8);
+        let glyphs = Psf2GlyphSet::render(&ttf_parser, GLYPH_COUNT_PAST_SURROGATES, 
+            &mut GlyphTimings::new(0)).unwrap();
+        assert_eq!(glyphs.len(), GLYPH_COUNT_PAST_SURROGATES as usize);
+        assert_eq!(glyphs[0x41].grapheme, "A");
+        assert_eq!(glyphs[0xd7ff].grapheme, "\u{d7ff}");
+        for surrogate in &glyphs[0xd800..0xd802] {
+            assert_eq!(surrogate.grapheme, "");
+            assert!(surrogate.data.iter().all(|b| *b == 0));
+        }
+    }
+
+    #[test]
+    fn dedup_merges_identical_bitmaps_and_their_table_entries() {
+        let glyph = |rows: &[&str], grapheme: &str| Glyph::from_ascii_art(rows, grapheme.to_string(), 2).unwrap();
+        let glyphs = vec![
+            glyph(&["#.", ".#"], "A"), 
+            glyph(&["##", ".."], "B"), 
+            glyph(&["#.", ".#"], "\u{391}"), 
+            glyph(&["#.", ".#"], "\u{410}"),
+        ];
+        let mut table = UnicodeTable::from_chars(['A', 'B', '\u{391}', '\u{410}']);
+        let glyphs = Psf2GlyphSet::dedup(glyphs, &mut table);
+        assert_eq!(glyphs.iter().map(|g| g.grapheme.as_str()).collect::<Vec<_>>(), ["A", "B"]);
+        assert_eq!(table.data, [vec!["A", "\u{391}", "\u{410}"], vec!["B"]]);
+    }
+
+    fn graphemes(graphemes: &[&str]) -> Vec<String> {
+        return graphemes.iter().map(|g| g.to_string()).collect();
+    }
+
+    #[test]
+    fn longest_keeps_first_listed_among_equally_long() {
+        let entry = graphemes(&["a", "ab", "cd", "e"]);
+        assert_eq!(RenderGrapheme::Longest.pick(&entry).unwrap(), "ab");
+        assert_eq!(RenderGrapheme::First.pick(&entry).unwrap(), "a");
+        assert_eq!(RenderGrapheme::Longest.pick(&[]), None);
+    }
+
+    #[test]
+    fn specific_falls_back_to_first() {
+        let entry = graphemes(&["a", "ab", "cd"]);
+        assert_eq!(RenderGrapheme::Specific(3).pick(&entry).unwrap(), "cd");
+        assert_eq!(RenderGrapheme::Specific(4).pick(&entry).unwrap(), "a");
+        assert_eq!(RenderGrapheme::Specific(1).to_string(), "specific:1");
+    }
+
+    #[test]
+    fn rendering_multi_codepoint_member_changes_bitmap() {
+        let ttf_parser = demo_font(16);
+        // 'B' is not in the demo font, so "AB" draws its `.notdef` box over the 'A'.
+        let table = UnicodeTable{data: vec![graphemes(&["A", "AB"])], meta: Default::default(), pinned_len: 0};
+        let render = |render_grapheme| Psf2GlyphSet::render_with_unicode_table(&ttf_parser, &table, render_grapheme, 
+            true, &mut GlyphTimings::new(0)).unwrap().remove(0);
+        let first = render(RenderGrapheme::First);
+        let longest = render(RenderGrapheme::Longest);
+        assert_eq!(first.grapheme, "A");
+        assert_eq!(longest.grapheme, "AB");
+        assert_ne!(first.data, longest.data);
+        assert_eq!(render(RenderGrapheme::Specific(2)).data, longest.data);
+    }
+
+    #[test]
+    fn pad_to_mode_resizes_the_one_odd_glyph() {
+        let glyph = |rows: &[&str]| Glyph::from_ascii_art(rows, String::from("x"), 3).unwrap();
+        let glyphs = vec![
+            glyph(&["#..", ".#.", "..#"]),
+            glyph(&["####", "#..#", "####"]),
+            glyph(&["..#", ".#.", "#.."]),
+            glyph(&["###", "...", "###"]),
+        ];
+        let glyph_set = Psf2GlyphSet::from_glyphs_pad_to_mode(glyphs).unwrap();
+        assert_eq!((glyph_set.height, glyph_set.width), (3, 3));
+        assert_eq!(glyph_set.glyphs()[1].to_ascii_art(), "###\n#..\n###");
+        assert_eq!(glyph_set.glyphs()[3].to_ascii_art(), "###\n...\n###");
+    }
+}
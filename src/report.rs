@@ -1,10 +1,22 @@
 use std::fmt::Display;
 use std::fmt::Formatter;
-use std::path::Path;
-use crate::ttf_parser::TtfParser;
-use crate::unicode_table::UnicodeTable;
+use crate::char_info::char_name;
+use crate::errors::GlyphError;
+use crate::glyph::Glyph;
+use crate::psf2_reader::{Psf2Check, RawPsf2Header};
+use crate::ttf_parser::{Coverage, FontMetrics, TtfParser};
+use crate::unicode_table::{UnicodeTable, UnicodeTableMeta};
 use unicode_blocks::UnicodeBlock;
 
+/// The number of blank columns between tiles in a glyph sheet.
+const SHEET_TILE_GAP: usize = 2;
+/// The number of blank columns between columns of a per-height table.
+const TABLE_COLUMN_GAP: usize = 2;
+/// Characters for increasing outline coverage in a `--gray-preview` sheet, from none to full.
+const GRAY_RAMP: [char; 8] = [' ', '.', ':', '-', '=', '+', '*', '#'];
+/// Font heights commonly used on consoles, at which `print_metrics` shows pixel sizes.
+const COMMON_HEIGHTS: [u32; 8] = [8, 12, 14, 16, 18, 20, 24, 32];
+
 #[derive(Debug)]
 pub struct GlyphReport {
     character: char,
@@ -21,10 +33,8 @@ impl GlyphReport {
 
 impl Display for GlyphReport {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        let pretty_unicode = format!("U+{:04x}", u32::from(self.character));
-        write!(f, "{} ({}): {}, {} x {} px", 
-            self.character, 
-            pretty_unicode, 
+        write!(f, "{}: {}, {} x {} px", 
+            char_name(self.character), 
             self.glyph_type, 
             self.height, 
             self.width,
@@ -50,27 +60,275 @@ impl Display for GlyphType {
 }
 
 
-pub fn report_char_vec(ttf_parser: TtfParser, characters: Vec<char>) -> () {
-    for c in characters.into_iter() {
+impl GlyphType {
+    /// A one-word description of the glyph type, for tables.
+    fn short_name(&self) -> &'static str {
+        match self {
+            Self::EmbeddedBitmap{..} => "bitmap",
+            Self::Vector => "vector",
+            Self::Undefined => "missing",
+        }
+    }
+}
+
+pub fn report_char_vec(ttf_parser: &TtfParser, characters: &[char]) {
+    for c in characters.iter().copied() {
         println!("{}", ttf_parser.report_char(c));
     }
 }
 
-pub fn report_unicode_block(ttf_parser: TtfParser, block: UnicodeBlock) -> () {
-    let block_characters: Vec<char> = (block.start() .. block.end())
+/// Prints a table with one row per character and one column per height, describing the glyph's
+/// type and size when the font is loaded at each height. `ttf_parsers` pairs each height with a
+/// parser loaded at that height.
+pub fn report_char_table(ttf_parsers: &[(u32, TtfParser)], characters: &[char]) {
+    let rows: Vec<(String, Vec<String>)> = characters.iter().copied()
+        .map(|c| (char_name(c), ttf_parsers.iter()
+            .map(|(_, parser)| {
+                let report = parser.report_char(c);
+                format!("{} {} x {}", report.glyph_type.short_name(), report.height, report.width)
+            })
+            .collect()))
+        .collect();
+    let headers: Vec<String> = ttf_parsers.iter().map(|(height, _)| format!("{} px", height)).collect();
+
+    let name_width = rows.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+    let cell_width = rows.iter().flat_map(|(_, cells)| cells.iter()).chain(headers.iter())
+        .map(|cell| cell.len()).max().unwrap_or(0) + TABLE_COLUMN_GAP;
+    let print_row = |name: &str, cells: &[String]| {
+        let cells: String = cells.iter().map(|cell| format!("{:cell_width$}", cell)).collect();
+        println!("{}", format!("{:name_width$}{:gap$}{}", name, "", cells, gap = TABLE_COLUMN_GAP).trim_end());
+    };
+    print_row("", &headers);
+    for (name, cells) in rows.iter() {
+        print_row(name, cells);
+    }
+}
+
+/// Prints the size and image format of each embedded bitmap strike in the font.
+pub fn report_strikes(ttf_parser: &TtfParser) {
+    let strikes = ttf_parser.strike_formats();
+    if strikes.is_empty() {
+        println!("No embedded bitmap strikes found; glyphs will be rasterized from outlines.");
+    }
+    for (ppem, format) in strikes.iter() {
+        println!("{} ppem: {:?}", ppem, format);
+    }
+}
+
+/// Prints how much of Unicode the font's cmap covers: the number of mapped codepoints and how
+/// many of them are embedded bitmaps or outlines, then, for each Unicode block with any mapped
+/// codepoints, how many of the block's codepoints are mapped.
+pub fn report_coverage(ttf_parser: &TtfParser) {
+    let mut blocks: Vec<(Option<UnicodeBlock>, u32)> = vec![];
+    let (mut mapped, mut embedded, mut vector) = (0, 0, 0);
+    for c in (0..=u32::from(char::MAX)).filter_map(char::from_u32).filter(|c| ttf_parser.is_defined(*c)) {
+        mapped += 1;
+        match ttf_parser.report_char(c).glyph_type {
+            GlyphType::EmbeddedBitmap{..} => embedded += 1,
+            GlyphType::Vector => vector += 1,
+            GlyphType::Undefined => (),
+        }
+        let block = unicode_blocks::find_unicode_block(c);
+        match blocks.last_mut() {
+            Some((last_block, count)) if *last_block == block => *count += 1,
+            _ => blocks.push((block, 1)),
+        }
+    }
+
+    println!("Mapped codepoints: {} ({} embedded bitmaps, {} outlines)", mapped, embedded, vector);
+    let rows: Vec<(&str, String)> = blocks.iter()
+        .map(|(block, count)| match block {
+            Some(b) => {
+                let size = b.end() - b.start() + 1;
+                let extent = if *count == size {"full"} else {"partial"};
+                (b.name(), format!("{}/{} {}", count, size, extent))
+            }
+            None => ("No block", count.to_string()),
+        })
+        .collect();
+    let name_width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, coverage) in rows.iter() {
+        println!("{:name_width$}{:gap$}{}", name, "", coverage, gap = TABLE_COLUMN_GAP);
+    }
+}
+
+/// Compares each character's embedded bitmap against its rasterized outline, and prints the
+/// `count` characters whose two renderings differ in the most pixels. Characters without both an
+/// embedded bitmap and an outline are skipped.
+pub fn report_strike_divergence(ttf_parser: &TtfParser, characters: &[char], count: usize) -> Result<(), GlyphError> {
+    let mut divergences: Vec<(char, u32)> = vec![];
+    for c in characters.iter().copied() {
+        if let Some(difference) = ttf_parser.strike_divergence(c)? {
+            divergences.push((c, difference));
+        }
+    }
+    if divergences.is_empty() {
+        println!("No characters have both an embedded bitmap and an outline to compare.");
+    }
+    // stable sort, so that ties stay in character order.
+    divergences.sort_by_key(|(_, difference)| std::cmp::Reverse(*difference));
+    for (c, difference) in divergences.into_iter().take(count) {
+        println!("{}: {} pixels differ", char_name(c), difference);
+    }
+    return Ok(());
+}
+
+/// Prints a font's vertical metrics in font units, then the cap height and x-height in pixels at
+/// each of `COMMON_HEIGHTS`.
+pub fn print_metrics(metrics: &FontMetrics) {
+    let describe = |units: Option<f32>, c: char| match units {
+        Some(u) => format!("{} units (measured from '{}')", u, c),
+        None => format!("unknown (no outline for '{}')", c),
+    };
+    println!("Units per em: {}", metrics.units_per_em);
+    println!("Ascent: {} units", metrics.ascent);
+    println!("Descent: {} units", metrics.descent);
+    println!("Cap height: {}", describe(metrics.cap_height, 'H'));
+    println!("x-height: {}", describe(metrics.x_height, 'x'));
+    println!();
+    for height in COMMON_HEIGHTS {
+        let px = |units: Option<f32>| units
+            .map(|u| format!("{:.1} px", metrics.to_px(u, height)))
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("At height {}: cap height = {}, x-height = {}", height, px(metrics.cap_height), px(metrics.x_height));
+    }
+}
+
+/// Prints the fields of a PSF2 header as a table, one field per row.
+pub fn print_psf2_header(header: &RawPsf2Header) {
+    let unicode_table = match header.unicode_table_exists() {
+        true => "Unicode table",
+        false => "no Unicode table",
+    };
+    let rows = [
+        ("Magic", (if header.magic_valid { "valid" } else { "INVALID" }).to_string()),
+        ("Version", header.version.to_string()),
+        ("Header size", format!("{} bytes", header.header_size)),
+        ("Flags", format!("{:#x} ({})", header.flags, unicode_table)),
+        ("Glyph count", header.glyph_count.to_string()),
+        ("Glyph size", format!("{} bytes", header.glyph_size)),
+        ("Height", format!("{} px", header.height)),
+        ("Width", format!("{} px", header.width)),
+    ];
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        println!("{:label_width$}{:gap$}{}", label, "", value, gap = TABLE_COLUMN_GAP);
+    }
+}
+
+/// Prints each check from `psf2_reader::verify_psf2` as a table row, marked `pass` or `FAIL`.
+pub fn print_psf2_checks(checks: &[Psf2Check]) {
+    let label_width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for c in checks {
+        println!("{:label_width$}{:gap$}{}{:gap$}{}", 
+            c.name, "", if c.passed {"pass"} else {"FAIL"}, "", c.detail, gap = TABLE_COLUMN_GAP);
+    }
+}
+
+/// The characters in a Unicode block, in order.
+pub fn unicode_block_chars(block: UnicodeBlock) -> Vec<char> {
+    return (block.start() .. block.end())
         .map(|i| char::from_u32(i).unwrap()).collect();
-    report_char_vec(ttf_parser, block_characters);
 }
 
-pub fn report_unicode_table(ttf_parser: TtfParser, unicode_table_file: &Path) 
-    -> Result<(), Box<dyn std::error::Error>> {
-    let unicode_table = UnicodeTable::from_file(unicode_table_file, None)?;
+/// The characters used to generate a PSF2 font from a Unicode mapping table, in order.
+pub fn unicode_table_chars(unicode_table: UnicodeTable) -> Vec<char> {
     // list of equiv graphemes has already been sorted by length, so the zeroth/reference grapheme 
     // will be single-character if possible
     let chars_to_report: Vec<char> = unicode_table.data.into_iter()
-        .map(|row| row[0].clone()) // acquire reference grapheme for each set of equiv graphemes
+        .filter_map(|row| row.first().cloned()) // acquire reference grapheme for each set of equiv graphemes
         .fold(String::new(), |acc, reference_grapheme| acc + &reference_grapheme)
         .chars().collect();
+    return chars_to_report;
+}
 
-    Ok(report_char_vec(ttf_parser, chars_to_report))
+/// Prints the metadata of a Unicode table file, one `Label: value` line per field that is set.
+pub fn print_unicode_table_meta(meta: &UnicodeTableMeta) {
+    for (label, value) in meta.fields() {
+        println!("{}: {}", label, value);
+    }
+}
+
+/// Prints a glyph's size, then each of its rows as hex bytes beside the row's ASCII art.
+pub fn print_glyph_bytes(glyph: &Glyph) {
+    println!("{} x {} px, {} bytes", glyph.height, glyph.width, glyph.data.len());
+    let row_length = std::cmp::max((glyph.width as f64 / 8.0).ceil() as usize, 1);
+    for (bytes, art) in glyph.data.chunks(row_length).zip(glyph.to_ascii_art().lines()) {
+        let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        println!("{}  {}", hex.join(" "), art);
+    }
+}
+
+/// Prints every character's glyph as an ASCII-art tile labeled with its codepoint, in a grid that
+/// wraps to fit within `terminal_width` columns. If `gray_preview` is set, each rasterized glyph
+/// is drawn next to its outline's coverage before thresholding, shaded with `GRAY_RAMP`.
+pub fn print_sheet(ttf_parser: &TtfParser, characters: &[char], terminal_width: usize, gray_preview: bool) 
+    -> Result<(), GlyphError> {
+    let mut tiles: Vec<(String, Vec<String>)> = vec![];
+    for c in characters.iter() {
+        let glyph = ttf_parser.render_char(*c)?;
+        let mut art: Vec<String> = glyph.to_ascii_art().lines().map(String::from).collect();
+        if gray_preview {
+            if let Some(coverage) = ttf_parser.coverage_char(*c)? {
+                let gray = gray_art(&coverage);
+                let width = art.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+                art.resize(std::cmp::max(art.len(), gray.len()), String::new());
+                for (line, gray_line) in art.iter_mut().zip(gray.iter()) {
+                    *line = format!("{:width$}{:gap$}{}", line, "", gray_line, gap = SHEET_TILE_GAP);
+                }
+            }
+        }
+        tiles.push((format!("U+{:04X}", u32::from(*c)), art));
+    }
+
+    let mut row: Vec<&(String, Vec<String>)> = vec![];
+    let mut row_width = 0;
+    for tile in tiles.iter() {
+        let tile_width = tile_width(tile);
+        if !row.is_empty() && row_width + tile_width > terminal_width {
+            print_sheet_row(&row);
+            row.clear();
+            row_width = 0;
+        }
+        row.push(tile);
+        row_width += tile_width;
+    }
+    if !row.is_empty() {
+        print_sheet_row(&row);
+    }
+    return Ok(());
+}
+
+/// Draws an outline's coverage as ASCII art, one `GRAY_RAMP` character per pixel.
+fn gray_art(coverage: &Coverage) -> Vec<String> {
+    let levels = (GRAY_RAMP.len() - 1) as f32;
+    return coverage.values
+        .chunks(std::cmp::max(coverage.width as usize, 1))
+        .map(|row| row.iter().map(|v| GRAY_RAMP[(v.clamp(0.0, 1.0) * levels).round() as usize]).collect())
+        .collect();
+}
+
+/// The number of columns a sheet tile takes up, including the gap after it.
+fn tile_width((label, art): &(String, Vec<String>)) -> usize {
+    let art_width = art.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    return std::cmp::max(label.len(), art_width) + SHEET_TILE_GAP;
+}
+
+/// Prints a row of sheet tiles side by side: a line of labels, then the tiles' art, then a blank
+/// line.
+fn print_sheet_row(row: &[&(String, Vec<String>)]) {
+    let height = row.iter().map(|(_, art)| art.len()).max().unwrap_or(0);
+    let mut lines = vec![String::new(); height + 1];
+    for tile in row.iter() {
+        let width = tile_width(tile);
+        let (label, art) = tile;
+        lines[0].push_str(&format!("{:width$}", label, width = width));
+        for (y, line) in lines[1..].iter_mut().enumerate() {
+            line.push_str(&format!("{:width$}", art.get(y).map(String::as_str).unwrap_or(""), width = width));
+        }
+    }
+    for line in lines.iter() {
+        println!("{}", line.trim_end());
+    }
+    println!();
 }
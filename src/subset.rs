@@ -0,0 +1,81 @@
+use itertools::Itertools;
+
+use crate::char_info::char_name;
+use crate::psf2_writer::{Psf2Font, Psf2GlyphSet, Psf2Header};
+use crate::unicode_table::{UnicodeTable, UnicodeTableMeta};
+
+/// Builds a font holding only the glyphs of `font` that represent `characters`, in the order the
+/// characters first ask for them, each with its whole Unicode table entry. Fonts without a Unicode
+/// table map glyph `i` to `U+i`; the subset always has a Unicode table, since its glyphs no longer
+/// sit at their codepoints. Returns an error if `font` has no glyph for one of `characters`.
+pub fn subset_font(font: Psf2Font, characters: &[char]) -> Result<Psf2Font, Box<dyn std::error::Error>> {
+    let glyph_count = font.glyphs.glyphs().len() as u32;
+    let unicode_table = font.unicode_table.unwrap_or_else(|| UnicodeTable::identity(glyph_count));
+    let indices: Vec<usize> = characters.iter()
+        .map(|c| unicode_table.data.iter()
+            .position(|group| group.iter().any(|g| g.chars().eq([*c])))
+            .ok_or_else(|| format!("{} is not mapped to any glyph in the font.", char_name(*c))))
+        .collect::<Result<Vec<usize>, String>>()?
+        .into_iter().unique().collect();
+
+    let glyphs = font.glyphs.glyphs();
+    let glyphs = Psf2GlyphSet::from_glyphs(indices.iter().map(|i| glyphs[*i].clone()).collect(), false)?;
+    let data: Vec<Vec<String>> = indices.iter().map(|i| unicode_table.data[*i].clone()).collect();
+    let header = Psf2Header{
+        unicode_table_exists: true,
+        glyph_count: data.len() as u32,
+        glyph_size: glyphs.length,
+        glyph_height: glyphs.height,
+        glyph_width: glyphs.width,
+    };
+    return Ok(Psf2Font{header, glyphs, unicode_table: Some(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0})});
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glyph::Glyph;
+
+    /// A font of one-row glyphs, each drawn by `art` and mapped to `graphemes`.
+    fn font(glyphs: &[(&str, &[&str])]) -> Psf2Font {
+        let glyph_set = Psf2GlyphSet::from_glyphs(glyphs.iter()
+            .map(|(art, _)| Glyph::from_ascii_art(&[art], String::new(), 1).unwrap())
+            .collect(), false).unwrap();
+        let data = glyphs.iter().map(|(_, graphemes)| graphemes.iter().map(|g| g.to_string()).collect()).collect();
+        let header = Psf2Header{
+            unicode_table_exists: true,
+            glyph_count: glyphs.len() as u32,
+            glyph_size: glyph_set.length,
+            glyph_height: glyph_set.height,
+            glyph_width: glyph_set.width,
+        };
+        return Psf2Font{header, glyphs: glyph_set, unicode_table: Some(UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0})};
+    }
+
+    #[test]
+    fn subset_renumbers_glyphs_in_request_order() {
+        let font = font(&[("#..", &["A"]), (".#.", &["B", "\u{392}"]), ("..#", &["C"])]);
+        let subset = subset_font(font, &['C', '\u{392}', 'C']).unwrap();
+        assert_eq!(subset.header.glyph_count, 2);
+        assert_eq!(subset.unicode_table.unwrap().data, [vec!["C"], vec!["B", "\u{392}"]]);
+        let art: Vec<String> = subset.glyphs.glyphs().iter().map(|g| g.to_ascii_art()).collect();
+        assert_eq!(art, ["..#", ".#."]);
+    }
+
+    #[test]
+    fn subset_without_table_uses_codepoints() {
+        let glyphs = Psf2GlyphSet::from_glyphs(["#..", ".#.", "..#"].iter()
+            .map(|art| Glyph::from_ascii_art(&[art], String::new(), 1).unwrap()).collect(), false).unwrap();
+        let header = Psf2Header{unicode_table_exists: false, glyph_count: 3, glyph_size: 1, glyph_height: 1, glyph_width: 3};
+        let subset = subset_font(Psf2Font{header, glyphs, unicode_table: None}, &['\u{2}']).unwrap();
+        assert_eq!(subset.unicode_table.unwrap().data, [vec!["\u{2}"]]);
+        assert_eq!(subset.glyphs.glyphs()[0].to_ascii_art(), "..#");
+    }
+
+    #[test]
+    fn subset_of_an_unmapped_character_is_an_error() {
+        let font = font(&[("#..", &["A"])]);
+        let error = subset_font(font, &['A', 'Z']).err().unwrap();
+        assert!(error.to_string().contains("is not mapped to any glyph"), "{}", error);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::glyph::Glyph;
+
+/// The number of glyphs in each row of an SVG contact sheet.
+const SVG_SHEET_COLUMNS: usize = 16;
+/// The size of one glyph pixel, in SVG user units.
+const SVG_PIXEL_SIZE: u32 = 4;
+/// The height of the codepoint label above each glyph, in SVG user units.
+const SVG_LABEL_HEIGHT: u32 = 14;
+/// The blank space around each glyph tile, in SVG user units.
+const SVG_TILE_MARGIN: u32 = 8;
+
+/// Draws `glyphs` as an SVG contact sheet: a grid of tiles, each labeled with its grapheme's
+/// codepoints (or its index, if it has none) above the glyph's set pixels. Each horizontal run
+/// of set pixels is one `<rect>`, to keep the file small.
+pub fn svg_sheet(glyphs: &[Glyph]) -> String {
+    let cell_width = glyphs.iter().map(|g| g.width).max().unwrap_or(0) * SVG_PIXEL_SIZE;
+    let cell_height = glyphs.iter().map(|g| g.height).max().unwrap_or(0) * SVG_PIXEL_SIZE;
+    // labels like "U+0065 U+0301" are wider than small glyphs.
+    let tile_width = std::cmp::max(cell_width, SVG_LABEL_HEIGHT * 4) + SVG_TILE_MARGIN;
+    let tile_height = cell_height + SVG_LABEL_HEIGHT + SVG_TILE_MARGIN;
+    let columns = std::cmp::min(glyphs.len(), SVG_SHEET_COLUMNS) as u32;
+    let rows = glyphs.len().div_ceil(SVG_SHEET_COLUMNS) as u32;
+
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+        font-family=\"monospace\" font-size=\"{}\">\n", 
+        columns * tile_width + SVG_TILE_MARGIN, rows * tile_height + SVG_TILE_MARGIN, SVG_LABEL_HEIGHT - 4);
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let left = (i % SVG_SHEET_COLUMNS) as u32 * tile_width + SVG_TILE_MARGIN;
+        let top = (i / SVG_SHEET_COLUMNS) as u32 * tile_height + SVG_TILE_MARGIN;
+        let label = match glyph.grapheme.is_empty() {
+            true => format!("#{}", i),
+            false => glyph.grapheme.chars().map(|c| format!("U+{:04X}", u32::from(c))).collect::<Vec<_>>().join(" "),
+        };
+        svg.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", left, top + SVG_LABEL_HEIGHT - 4, label));
+
+        let glyph_top = top + SVG_LABEL_HEIGHT;
+        for y in 0..glyph.height {
+            let mut x = 0;
+            while x < glyph.width {
+                if !glyph.get_pixel(x, y) {
+                    x += 1;
+                    continue;
+                }
+                let run_start = x;
+                while x < glyph.width && glyph.get_pixel(x, y) {
+                    x += 1;
+                }
+                svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n", 
+                    left + run_start * SVG_PIXEL_SIZE, glyph_top + y * SVG_PIXEL_SIZE, 
+                    (x - run_start) * SVG_PIXEL_SIZE, SVG_PIXEL_SIZE));
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    return svg;
+}
@@ -1,57 +1,636 @@
-use ab_glyph::{point, PxScale, FontVec, Font, ScaleFont};
+use ab_glyph::{point, GlyphId, PxScale, FontVec, Font, ScaleFont};
 use ab_glyph::PxScaleFont;
+use ab_glyph::GlyphImageFormat;
 
 use bitvec::prelude::*;
+use clap::ValueEnum;
 
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
 use std::path::Path;
 
+use crate::char_info::char_name;
 use crate::glyph;
 use crate::errors::TtfParserError;
 use crate::errors::GlyphError;
 use crate::report::GlyphReport;
 use crate::report::GlyphType;
 
+/// The largest size at which `TtfParser::strike_formats` looks for embedded bitmap strikes.
+const STRIKE_SAMPLE_MAX_SIZE: u16 = 512;
+/// The largest canvas, in pixels, that `rasterize` will allocate for a single glyph. Guards
+/// against pathological fonts and mistyped heights.
+const MAX_GLYPH_PIXELS: u32 = 1 << 24;
+/// The number of glyph ids `TtfParser::strike_formats` tries when looking for one with bitmaps.
+const STRIKE_SAMPLE_GLYPH_COUNT: u16 = 16;
+/// The number of glyph ids `TtfParser::has_advance_metrics` checks for a nonzero advance width.
+const ADVANCE_SAMPLE_GLYPH_COUNT: u16 = 256;
+/// The default fraction of a pixel an outline must cover for `rasterize` to set it.
+pub const COVERAGE_THRESHOLD: f32 = 0.5;
+
 /// A parser that creates `Glyph`s from a TTF/OTF font and a character set.
 #[derive(Debug)]
 pub struct TtfParser {
     /// TTF input font.
     font: PxScaleFont<FontVec>,
+    /// Fonts to render characters from, in order, when `font` does not define them. Loaded at
+    /// the same pixel height as `font`.
+    fallbacks: Vec<TtfParser>,
+    /// Whether to render characters that no font defines as U+FFFD REPLACEMENT CHARACTER.
+    fallback_to_fffd: bool,
+    /// Whether to render characters that no font defines as blank cells.
+    blank_missing: bool,
+    /// How to handle embedded bitmaps whose strike height differs from the requested height. If
+    /// `None`, such bitmaps are used at their own height.
+    strike_height_policy: Option<StrikeHeightPolicy>,
+    /// Whether composing a multi-character grapheme from glyphs of different sizes is an error,
+    /// rather than padding the smaller glyphs to fit.
+    strict_compose: bool,
+    /// How wide to make the canvas of each rasterized glyph.
+    cell_width: CellWidth,
+    /// The fraction of a pixel an outline must cover for `rasterize` to set it.
+    coverage_threshold: f32,
+    /// How `rasterize` flattens partial coverage to one bit.
+    raster_mode: RasterMode,
+    /// How many times larger than the requested height outlines are drawn, in each direction,
+    /// before each block of pixels is averaged into one pixel of the canvas.
+    supersample: u32,
+    /// The number of pixels to move every rasterized outline down its canvas from where the
+    /// font's ascent puts it; negative values move outlines up.
+    vertical_offset: i32,
+    /// The number of glyphs rasterized from this font whose outlines partly covered some pixels,
+    /// and so were flattened to one bit at `coverage_threshold`.
+    flattened_glyphs: Cell<u32>,
+    /// The glyphs rasterized from this font whose outlines were clipped by their canvas.
+    clipped_glyphs: RefCell<Vec<ClippedGlyph>>,
+}
+
+/// How many pixels of a rasterized outline, covered at least the coverage threshold, fell outside
+/// its canvas on each side. Pixels past a corner count as above or below.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Clipping {
+    pub above: u32,
+    pub below: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl Clipping {
+    /// The number of clipped pixels on all sides.
+    pub fn total(&self) -> u32 {
+        return self.above + self.below + self.left + self.right;
+    }
+}
+
+/// A rasterized glyph whose outline was clipped by its canvas, as listed by
+/// `TtfParser::clipped_glyphs`.
+#[derive(Debug, Clone)]
+pub struct ClippedGlyph {
+    /// The name of the glyph's character, or its glyph id.
+    pub description: String,
+    pub clipping: Clipping,
+}
+
+impl Display for ClippedGlyph {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let sides = [
+            (self.clipping.above, "above"), 
+            (self.clipping.below, "below"), 
+            (self.clipping.left, "left of"), 
+            (self.clipping.right, "right of"),
+        ];
+        let clipped = sides.iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, side)| format!("{} pixels {} the cell", count, side))
+            .collect::<Vec<String>>()
+            .join(", ");
+        return write!(f, "{} clipped {}", self.description, clipped);
+    }
+}
+
+/// How much of each pixel of a glyph's canvas its outline covers, as measured by
+/// `TtfParser::coverage`.
+pub struct Coverage {
+    pub width: u32,
+    pub height: u32,
+    /// The covered fraction of each pixel, from 0.0 to 1.0, row by row.
+    pub values: Vec<f32>,
+    /// Whether every pixel the outline touched was either fully covered or not at all.
+    pub pixel_perfect: bool,
+    /// The pixels of the outline that fell outside the canvas.
+    pub clipping: Clipping,
+    pub bearing_x: Option<i32>,
+    pub bearing_y: Option<i32>,
+    /// The horizontal distance in pixels from the glyph origin to the left edge of the canvas.
+    pub x_offset: i32,
+}
+
+/// A font's vertical metrics, in font units, as reported by `TtfParser::metrics`.
+#[derive(Debug)]
+pub struct FontMetrics {
+    pub units_per_em: f32,
+    /// The distance from the baseline up to the top of the font's canvas.
+    pub ascent: f32,
+    /// The distance from the baseline down to the bottom of the font's canvas; usually negative.
+    pub descent: f32,
+    /// The height of the ink of 'H' above the baseline, if the font has an outline for it.
+    pub cap_height: Option<f32>,
+    /// The height of the ink of 'x' above the baseline, if the font has an outline for it.
+    pub x_height: Option<f32>,
+}
+
+impl FontMetrics {
+    /// The size in pixels of a distance of `units` font units, when the font is converted at
+    /// `height` pixels. The height spans the ascent to the descent, not the em square.
+    pub fn to_px(&self, units: f32, height: u32) -> f32 {
+        return units * height as f32 / (self.ascent - self.descent);
+    }
+
+    /// The height in pixels, from the ascent to the descent, at which `size_metric` comes out
+    /// `size` pixels tall, rounded to the nearest pixel. Returns `None` for `SizeMetric::Cap` if
+    /// the cap height is unknown.
+    pub fn cell_height(&self, size: u32, size_metric: SizeMetric) -> Option<u32> {
+        let metric_units = match size_metric {
+            SizeMetric::Cell => return Some(size),
+            SizeMetric::Em => self.units_per_em,
+            SizeMetric::Cap => self.cap_height?,
+        };
+        return Some((size as f32 * (self.ascent - self.descent) / metric_units).round() as u32);
+    }
+}
+
+/// Which of a font's measurements the requested height sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SizeMetric {
+    /// The whole cell, from the font's ascent to its descent.
+    Cell,
+    /// The em square, the size fonts are usually quoted in.
+    Em,
+    /// The height of capital letters, as measured from 'H'. The cell is taller.
+    Cap,
+}
+
+/// Where the width of a rasterized glyph's canvas comes from. Glyphs that end up narrower than
+/// others still need `--pad` to fit a uniform cell; glyphs that end up wider are clipped only
+/// with `Em`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CellWidth {
+    /// The glyph's advance width. Ink that overhangs the advance is clipped.
+    Advance,
+    /// The width of the glyph's ink, so no ink is clipped horizontally. Glyphs without an
+    /// outline, such as spaces, use their advance width instead.
+    Ink,
+    /// The font's em width, the same for every glyph. Ink beyond the em is clipped.
+    Em,
+}
+
+/// How a rasterized outline's partial pixel coverage is flattened to one bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RasterMode {
+    /// Set each pixel whose coverage reaches the coverage threshold.
+    Threshold,
+    /// Floyd–Steinberg dithering: compare each pixel with the coverage threshold, and spread the
+    /// difference between its coverage and its new value to the pixels right of and below it.
+    Dither,
+}
+
+/// How to handle an embedded bitmap strike whose height differs from the requested height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StrikeHeightPolicy {
+    /// Exit with an error.
+    Exact,
+    /// Rescale the bitmap to the requested height, keeping its aspect ratio.
+    Scale,
+    /// Pad the bitmap with blank rows at the bottom, or crop rows from the bottom.
+    Pad,
 }
 
 impl TtfParser {
     pub fn from_font_path(font_path: &Path, height: u32) -> Result<TtfParser, TtfParserError> {
-        let font_px_scale = PxScale::from(height as f32);
         let font_data = std::fs::read(font_path)?;
+        return Self::from_bytes(font_data, height);
+    }
+
+    /// Creates a parser from the contents of a TTF/OTF font file already in memory, e.g. a font
+    /// bundled with `include_bytes!`.
+    pub fn from_bytes(font_data: Vec<u8>, height: u32) -> Result<TtfParser, TtfParserError> {
+        let font_px_scale = PxScale::from(height as f32);
         let font = FontVec::try_from_vec_and_index(font_data, 0)?;
         let scaled_font = font.into_scaled(font_px_scale);
         
-        return Ok(Self{font: scaled_font})
+        return Ok(Self{font: scaled_font, fallbacks: vec![], fallback_to_fffd: false, blank_missing: false, strike_height_policy: None, strict_compose: false, cell_width: CellWidth::Advance, coverage_threshold: COVERAGE_THRESHOLD, raster_mode: RasterMode::Threshold, supersample: 1, vertical_offset: 0, flattened_glyphs: Cell::new(0), clipped_glyphs: RefCell::new(vec![])})
+    }
+
+    /// Adds a fallback font, to be used for characters that neither this font nor any earlier
+    /// fallback defines.
+    pub fn add_fallback(&mut self, font_path: &Path) -> Result<(), TtfParserError> {
+        let fallback = Self::from_font_path(font_path, self.font.scale().y as u32)?;
+        self.fallbacks.push(fallback);
+        return Ok(());
+    }
+
+    /// Checks that every fallback font renders U+0020 SPACE in a cell of the same size as this
+    /// font, so that glyphs taken from it fit the font without `--pad`. Only the space cells are
+    /// compared, so other glyphs of a fallback font may still differ in size. Call after the other
+    /// settings, since the cell width depends on them.
+    pub fn check_fallback_cells(&self) -> Result<(), TtfParserError> {
+        let Ok(expected) = self.space_cell() else {
+            return Ok(());
+        };
+        for (i, fallback) in self.fallbacks.iter().enumerate() {
+            let Ok(cell) = fallback.space_cell() else {
+                continue;
+            };
+            if (cell.height, cell.width) != (expected.height, expected.width) {
+                return Err(TtfParserError::FallbackCellSize{fallback: i + 1, height: cell.height, width: cell.width, 
+                    expected_height: expected.height, expected_width: expected.width});
+            }
+        }
+        return Ok(());
+    }
+
+    /// Sets whether characters that neither this font nor any fallback defines are rendered as
+    /// U+FFFD REPLACEMENT CHARACTER (or a box, if no font defines U+FFFD either).
+    pub fn set_fallback_to_fffd(&mut self, fallback_to_fffd: bool) {
+        self.fallback_to_fffd = fallback_to_fffd;
+    }
+
+    /// Sets whether characters that neither this font nor any fallback defines are rendered as
+    /// blank glyphs the size of the default cell, instead of the font's `.notdef` glyph.
+    pub fn set_blank_missing(&mut self, blank_missing: bool) {
+        self.blank_missing = blank_missing;
     }
 
+    /// Sets where the width of each rasterized glyph's canvas comes from.
+    pub fn set_cell_width(&mut self, cell_width: CellWidth) {
+        self.cell_width = cell_width;
+        for fallback in self.fallbacks.iter_mut() {
+            fallback.set_cell_width(cell_width);
+        }
+    }
+
+    /// Sets the fraction of a pixel an outline must cover for the pixel to be set, in this font and
+    /// all of its fallbacks. Grayscale embedded bitmaps are flattened at the same threshold.
+    pub fn set_coverage_threshold(&mut self, coverage_threshold: f32) {
+        self.coverage_threshold = coverage_threshold;
+        for fallback in self.fallbacks.iter_mut() {
+            fallback.set_coverage_threshold(coverage_threshold);
+        }
+    }
+
+    /// Sets how rasterized outlines are flattened to one bit, in this font and all of its
+    /// fallbacks.
+    pub fn set_raster_mode(&mut self, raster_mode: RasterMode) {
+        self.raster_mode = raster_mode;
+        for fallback in self.fallbacks.iter_mut() {
+            fallback.set_raster_mode(raster_mode);
+        }
+    }
+
+    /// Sets how many times larger outlines are drawn before being averaged down to the requested
+    /// height, in this font and all of its fallbacks. `1` draws them at the requested height.
+    pub fn set_supersample(&mut self, supersample: u32) {
+        self.supersample = supersample;
+        for fallback in self.fallbacks.iter_mut() {
+            fallback.set_supersample(supersample);
+        }
+    }
+
+    /// Sets the number of pixels to move every rasterized outline down (or, if negative, up) its
+    /// canvas, in this font and all of its fallbacks. Does not affect embedded bitmaps.
+    pub fn set_vertical_offset(&mut self, vertical_offset: i32) {
+        self.vertical_offset = vertical_offset;
+        for fallback in self.fallbacks.iter_mut() {
+            fallback.set_vertical_offset(vertical_offset);
+        }
+    }
+
+    /// The fraction of a pixel an outline must cover for the pixel to be set.
+    pub fn coverage_threshold(&self) -> f32 {
+        return self.coverage_threshold;
+    }
+
+    /// Sets whether `render_string` returns an error, rather than padding, when the characters of
+    /// a grapheme render at different sizes.
+    pub fn set_strict_compose(&mut self, strict_compose: bool) {
+        self.strict_compose = strict_compose;
+    }
+
+    /// Sets how embedded bitmaps whose strike height differs from the requested height are handled,
+    /// in this font and all of its fallbacks.
+    pub fn set_strike_height_policy(&mut self, strike_height_policy: Option<StrikeHeightPolicy>) {
+        self.strike_height_policy = strike_height_policy;
+        for fallback in self.fallbacks.iter_mut() {
+            fallback.set_strike_height_policy(strike_height_policy);
+        }
+    }
+
+    /// Finds the largest height, up to `max_height`, at which the ink of every character in
+    /// `characters` fits inside its rasterized canvas without being clipped.
+    pub fn fit_height(font_path: &Path, max_height: u32, characters: &[char]) -> Result<u32, TtfParserError> {
+        let font_data = std::fs::read(font_path)?;
+        let font = FontVec::try_from_vec_and_index(font_data, 0)?;
+        for height in (1..=max_height).rev() {
+            let scaled_font = font.as_scaled(PxScale::from(height as f32));
+            if characters.iter().all(|c| ink_fits_canvas(&scaled_font, *c)) {
+                return Ok(height);
+            }
+        }
+        return Err(TtfParserError::NoFittingHeight{max_height});
+    }
+
+    /// The number of glyph ids in the font at `font_path`, including glyphs that no character
+    /// maps to.
+    pub fn glyph_id_count(font_path: &Path) -> Result<u32, TtfParserError> {
+        let font_data = std::fs::read(font_path)?;
+        let font = FontVec::try_from_vec_and_index(font_data, 0)?;
+        return Ok(font.glyph_count() as u32);
+    }
+
+    /// Reads the vertical metrics of the font at `font_path`. ab_glyph does not expose the OS/2
+    /// cap height and x-height, so they are measured from the outlines of 'H' and 'x'.
+    pub fn metrics(font_path: &Path) -> Result<FontMetrics, TtfParserError> {
+        let font_data = std::fs::read(font_path)?;
+        let font = FontVec::try_from_vec_and_index(font_data, 0)?;
+        // outline bounds are in font units with y pointing up, so `min.y` is the top of the ink.
+        let ink_top = |c: char| Some(font.glyph_id(c))
+            .filter(|id| *id != GlyphId(0))
+            .and_then(|id| font.outline(id))
+            .map(|outline| outline.bounds.min.y);
+        return Ok(FontMetrics{
+            units_per_em: font.units_per_em().unwrap_or(1000.0),
+            ascent: font.ascent_unscaled(),
+            descent: font.descent_unscaled(),
+            cap_height: ink_top('H'),
+            x_height: ink_top('x'),
+        });
+    }
+
+    /// The number of glyphs rendered so far, from this font or its fallbacks, whose anti-aliased
+    /// outlines were flattened to one bit.
+    pub fn flattened_glyph_count(&self) -> u32 {
+        return self.flattened_glyphs.get() + self.fallbacks.iter().map(|f| f.flattened_glyph_count()).sum::<u32>();
+    }
+
+    /// The glyphs rendered so far, from this font or its fallbacks, whose rasterized outlines were
+    /// clipped by their canvas.
+    pub fn clipped_glyphs(&self) -> Vec<ClippedGlyph> {
+        return self.clipped_glyphs.borrow().iter().cloned()
+            .chain(self.fallbacks.iter().flat_map(|f| f.clipped_glyphs()))
+            .collect();
+    }
+
+    /// Checks whether any of `characters` maps to a glyph other than glyph 0 (`.notdef`). If none
+    /// do, the font probably lacks a Unicode cmap.
+    pub fn maps_any(&self, characters: &[char]) -> bool {
+        return characters.iter().any(|c| self.font.glyph_id(*c) != GlyphId(0));
+    }
+
+    /// Lists the font's embedded bitmap strikes as pairs of pixels-per-em size and image format,
+    /// sorted by size. ab_glyph can't list strikes directly, so this samples a representative
+    /// glyph at every size up to `STRIKE_SAMPLE_MAX_SIZE` and records each strike it is given.
+    pub fn strike_formats(&self) -> Vec<(u16, GlyphImageFormat)> {
+        // glyph 0 is `.notdef`, which often has no bitmap, so look for 'A' or the first few glyphs
+        // after it.
+        let mut candidates = vec![self.font.glyph_id('A')];
+        candidates.extend((1..=STRIKE_SAMPLE_GLYPH_COUNT).map(GlyphId));
+        let mut strikes: BTreeMap<u16, GlyphImageFormat> = BTreeMap::new();
+        for glyph_id in candidates.into_iter().filter(|id| *id != GlyphId(0)) {
+            for size in 1..=STRIKE_SAMPLE_MAX_SIZE {
+                if let Some(image) = self.font.font.glyph_raster_image2(glyph_id, size) {
+                    strikes.entry(image.pixels_per_em).or_insert(image.format);
+                }
+            }
+            if !strikes.is_empty() {
+                break;
+            }
+        }
+        return strikes.into_iter().collect();
+    }
+
+    /// Checks whether any of the font's first glyphs has a nonzero advance width. Some broken fonts
+    /// report zero advance for every glyph, so that glyphs as wide as their advance have no pixels.
+    pub fn has_advance_metrics(&self) -> bool {
+        let sample_count = std::cmp::min(self.font.font.glyph_count(), ADVANCE_SAMPLE_GLYPH_COUNT as usize) as u16;
+        return (0..sample_count).any(|id| self.font.h_advance(GlyphId(id)) > 0.0);
+    }
+
+    /// The number of pixels above the baseline in each rasterized glyph's canvas.
+    pub fn ascent(&self) -> u32 {
+        return self.font.ascent().round().max(0.0) as u32;
+    }
+
+    /// The pixels-per-em size that outlines are rasterized at: the requested font height.
+    pub fn ppem(&self) -> u32 {
+        return self.font.scale().y.round() as u32;
+    }
+
+    /// Renders a grapheme by combining the glyphs of its characters. Zero-width combining marks
+    /// are placed where the font positions them relative to the first (base) character, raised
+    /// clear of its ink if needed (see `place_mark`); other characters are overlaid from the top
+    /// left of the base glyph.
     pub fn render_string(&self, grapheme: &str) -> Result<glyph::Glyph, GlyphError> {
-        let mut char_glyphs = grapheme.chars().map(|c| self.render_char(c));
-        let first_glyph = char_glyphs.nth(0);
-        return match first_glyph {
-            Some(fg) => { 
-                let combined_glyph = char_glyphs.fold(fg, |acc, g| acc.add(g).unwrap());
-                Ok(combined_glyph)
+        let mut characters = grapheme.chars();
+        let Some(base) = characters.next() else {
+            return Err(GlyphError::EmptyString);
+        };
+        let mut combined_glyph = self.render_char(base)?;
+        let pen_column = self.pen_column(base);
+        for c in characters {
+            if let Some((mark, left)) = self.render_mark(c)? {
+                combined_glyph = self.place_mark(combined_glyph, mark, pen_column + left);
+                continue;
+            }
+            let g = self.render_char(c)?;
+            combined_glyph = match self.strict_compose {
+                true => combined_glyph.add(g),
+                false => combined_glyph.overlay(g),
+            }.map_err(|inner| GlyphError::Compose{grapheme: grapheme.to_string(), inner: Box::new(inner)})?;
+        }
+        return Ok(combined_glyph);
+    }
+
+    /// The column of the canvas of `character` at which its advance ends: the pen position that
+    /// the font places combining marks after it from.
+    fn pen_column(&self, character: char) -> i32 {
+        let parser = self.parser_for(character).unwrap_or(self);
+        let glyph_id = parser.font.glyph_id(character);
+        // with CellWidth::Ink, the canvas starts at the left edge of the ink, not the origin.
+        let origin = match parser.cell_width {
+            CellWidth::Ink => parser.font.outline_glyph(glyph_id.with_scale_and_position(parser.font.height(), point(0.0, 0.0)))
+                .map_or(0.0, |og| og.px_bounds().min.x),
+            _ => 0.0,
+        };
+        return (parser.font.h_advance(glyph_id) - origin).round() as i32;
+    }
+
+    /// Rasterizes `character` on a canvas cropped to its ink, if it is a zero-width combining
+    /// mark drawn from an outline. Returns the bitmap and the column of its left edge relative to
+    /// the pen position, or `None` for characters with an advance width or an embedded bitmap.
+    fn render_mark(&self, character: char) -> Result<Option<(glyph::Glyph, i32)>, GlyphError> {
+        let parser = self.parser_for(character).unwrap_or(self);
+        let glyph_id = parser.font.glyph_id(character);
+        if parser.font.h_advance(glyph_id) != 0.0 
+            || parser.font.font.glyph_raster_image2(glyph_id, parser.font.height().ceil() as u16).is_some() {
+            return Ok(None);
+        }
+        let Some(outline) = parser.font.outline_glyph(glyph_id.with_scale_and_position(parser.font.height(), point(0.0, 0.0))) else {
+            return Ok(None);
+        };
+        let mark = parser.rasterize(glyph_id, CellWidth::Ink, character.to_string(), &char_name(character))?;
+        return Ok(Some((mark, outline.px_bounds().min.x as i32)));
+    }
+
+    /// ORs the bitmap of a combining mark into `base`, with its left edge at `column`. A mark
+    /// above the baseline that would touch the ink below it, e.g. an accent designed for
+    /// lowercase letters over a capital, is raised to leave one blank row, as far as the top of
+    /// the canvas allows.
+    fn place_mark(&self, base: glyph::Glyph, mark: glyph::Glyph, column: i32) -> glyph::Glyph {
+        let ink_rows: Vec<u32> = mark.rows().enumerate()
+            .filter(|(_, row)| row.any())
+            .map(|(y, _)| y as u32)
+            .collect();
+        let (Some(&mark_top), Some(&mark_bottom)) = (ink_rows.first(), ink_rows.last()) else {
+            return base.overlay_at(mark, column as i64, 0);
+        };
+        let baseline = self.ascent() as i64 + self.vertical_offset as i64;
+        let columns = std::cmp::max(column, 0) as u32..std::cmp::min(column as i64 + mark.width as i64, base.width as i64).max(0) as u32;
+        let base_top = (0..base.height).find(|y| columns.clone().any(|x| base.get_pixel(x, *y)));
+        let lift = match base_top {
+            Some(top) if (mark_bottom as i64) < baseline && mark_bottom + 1 >= top => 
+                std::cmp::min(mark_bottom + 2 - top, mark_top),
+            _ => 0,
+        };
+        return base.overlay_at(mark, column as i64, -(lift as i64));
+    }
+
+    pub fn render_char(&self, character: char) -> Result<glyph::Glyph, GlyphError> {
+        let parser = match self.parser_for(character) {
+            Some(p) => p,
+            None => {
+                if !self.fallbacks.is_empty() {
+                    log::warn!("{} is not defined in the input font or any fallback font.", 
+                        char_name(character));
+                }
+                if self.blank_missing {
+                    return self.render_blank(character);
+                }
+                if self.fallback_to_fffd && !is_blank_space(character) {
+                    return self.render_replacement(character);
+                }
+                self
+            }
+        };
+        let glyph_id = parser.font.glyph_id(character);
+        let glyph = parser.render_glyph(glyph_id, character.to_string(), &char_name(character))?;
+        if is_blank_space(character) {
+            return parser.blank_cell(glyph, character);
+        }
+        return Ok(glyph);
+    }
+
+    /// Renders `character` as a blank glyph the size of the font's default cell (that of U+0020
+    /// SPACE), whatever the font's glyph for it looks like.
+    pub fn render_blank(&self, character: char) -> Result<glyph::Glyph, GlyphError> {
+        let reference = self.space_cell()?;
+        return Ok(glyph::Glyph::blank(reference.height, reference.width, character.to_string(), reference.ppem));
+    }
+
+    /// Checks whether this font or any of its fallbacks defines `character`.
+    pub fn is_defined_in_chain(&self, character: char) -> bool {
+        return self.parser_for(character).is_some();
+    }
+
+    /// Picks the first font in the fallback chain that defines `character`, if any.
+    fn parser_for(&self, character: char) -> Option<&TtfParser> {
+        if self.is_defined(character) {
+            return Some(self);
+        }
+        return self.fallbacks.iter().find(|f| f.is_defined(character));
+    }
+
+    /// Renders U+FFFD REPLACEMENT CHARACTER in place of `character`, from the first font in the
+    /// fallback chain that defines it. If no font does, draws a box in the cell of U+0020 SPACE.
+    fn render_replacement(&self, character: char) -> Result<glyph::Glyph, GlyphError> {
+        let description = format!("U+FFFD in place of {}", char_name(character));
+        return match self.parser_for('\u{fffd}') {
+            Some(p) => p.render_glyph(p.font.glyph_id('\u{fffd}'), character.to_string(), &description),
+            None => {
+                let reference = self.space_cell()?;
+                Ok(glyph::Glyph::outline_box(reference.height, reference.width, character.to_string(), reference.ppem))
             }
-            None => Err(GlyphError::EmptyString),
         }
     }
 
-    pub fn render_char(&self, character: char) -> glyph::Glyph {
-        let embedded_bitmap = self.find_embedded_bitmap(character);
+    /// Replaces the rendering of a space character (see `is_blank_space`) with a blank glyph, so
+    /// that space-like characters never contain stray ink. If the character has no glyph in the
+    /// font or has zero advance width, the cell of U+0020 SPACE is used instead, so that its
+    /// dimensions still match the rest of the set.
+    fn blank_cell(&self, glyph: glyph::Glyph, character: char) -> Result<glyph::Glyph, GlyphError> {
+        let degenerate = glyph.width == 0 || self.font.glyph_id(character) == GlyphId(0);
+        let reference = match degenerate && character != ' ' {
+            true => self.space_cell()?,
+            false => glyph,
+        };
+        return Ok(glyph::Glyph::blank(reference.height, reference.width, character.to_string(), reference.ppem));
+    }
+
+    /// Renders U+0020 SPACE, whose canvas is the font's default cell.
+    fn space_cell(&self) -> Result<glyph::Glyph, GlyphError> {
+        return self.render_glyph(self.font.glyph_id(' '), String::new(), &char_name(' '));
+    }
+
+    /// The width of the rendered U+0020 SPACE, which is the cell width of a monospace font.
+    /// Returns an error if the font has no glyph for space.
+    pub fn space_width(&self) -> Result<u32, GlyphError> {
+        if self.font.glyph_id(' ') == GlyphId(0) {
+            return Err(GlyphError::NoSpaceGlyph);
+        }
+        return Ok(self.render_char(' ')?.width);
+    }
+
+    /// Renders a glyph by its id in the font, regardless of which characters (if any) map to it.
+    /// The resulting glyph's grapheme is empty.
+    pub fn render_glyph_id(&self, glyph_id: u16) -> Result<glyph::Glyph, GlyphError> {
+        return self.render_glyph(GlyphId(glyph_id), String::new(), &format!("glyph id {}", glyph_id));
+    }
+
+    /// Renders `glyph_id` from an embedded bitmap if there is one, or from its outline otherwise.
+    /// `description` names the glyph in log messages.
+    fn render_glyph(&self, glyph_id: GlyphId, grapheme: String, description: &str) -> Result<glyph::Glyph, GlyphError> {
+        let embedded_bitmap = self.find_embedded_bitmap(glyph_id, &grapheme, description)?;
         return match embedded_bitmap {
-            Some(b) => b,
-            None => self.rasterize(character),
+            Some(b) => Ok(b),
+            None => self.rasterize(glyph_id, self.cell_width, grapheme, description),
         }
     }
 
+    /// Renders `character` from both its embedded bitmap and its outline, and counts the pixels
+    /// that differ between the two. Returns `None` if the font lacks either a usable embedded
+    /// bitmap or an outline for the character.
+    pub fn strike_divergence(&self, character: char) -> Result<Option<u32>, GlyphError> {
+        let glyph_id = self.font.glyph_id(character);
+        let description = char_name(character);
+        if self.font.outline_glyph(glyph_id.with_scale(self.font.height())).is_none() {
+            return Ok(None);
+        }
+        let Some(strike) = self.find_embedded_bitmap(glyph_id, &character.to_string(), &description)? else {
+            return Ok(None);
+        };
+        let outline = self.rasterize(glyph_id, self.cell_width, character.to_string(), &description)?;
+        return Ok(Some(strike.pixel_difference(&outline)));
+    }
+
+    /// Checks whether the font defines a glyph for `character`, i.e. whether the character maps to
+    /// a different glyph than a known gap in Unicode does.
+    pub fn is_defined(&self, character: char) -> bool {
+        return self.font.glyph_id(character) != self.font.glyph_id('\u{03a2}');
+    }
+
     pub fn report_char(&self, character: char) -> GlyphReport {
         let glyph_id = self.font.glyph_id(character);
-        // check whether this character maps to the same glyph as a known gap in Unicode.
-        let glyph_is_undefined = glyph_id == self.font.glyph_id('\u{03a2}');
+        let glyph_is_undefined = !self.is_defined(character);
 
         let glyph_image = self.font.font.glyph_raster_image2(glyph_id, self.font.height().ceil() as u16);
         let (glyph_type, height, width) = match glyph_image {
@@ -72,69 +651,447 @@ impl TtfParser {
         return GlyphReport::new(character, glyph_type, height, width);
     }
 
-    
-    fn find_embedded_bitmap(&self, character: char) -> Option<glyph::Glyph> {
-        let glyph_id = self.font.glyph_id(character);
-        let glyph_image = self.font.font.glyph_raster_image2(glyph_id, self.font.height().ceil() as u16)?;
-        let glyph = glyph::Glyph::from_glyph_image(glyph_image, character);
-        return match glyph {
-            Ok(g) => Some(g),
+    /// Loads the embedded bitmap for `glyph_id` from the strike closest to the requested height,
+    /// if there is one, and reconciles its height with the requested height (see
+    /// `fit_strike_height`).
+    fn find_embedded_bitmap(&self, glyph_id: GlyphId, grapheme: &str, description: &str) 
+        -> Result<Option<glyph::Glyph>, GlyphError> {
+        let Some(glyph_image) = self.font.font.glyph_raster_image2(glyph_id, self.font.height().ceil() as u16) else {
+            return Ok(None);
+        };
+        let glyph = match glyph::Glyph::from_glyph_image(glyph_image, grapheme, self.coverage_threshold) {
+            Ok(g) => g,
             Err(e) => {
-                eprintln!("{e} -- rasterizing instead"); // TODO make this pretty, probably via
-                                                         // logging.
-                None
+                log::warn!("While loading embedded bitmap for {}: {e} -- rasterizing instead", description);
+                return Ok(None);
             }
+        };
+
+        return self.fit_strike_height(glyph).map(Some);
+    }
+
+    /// Reconciles the height of an embedded bitmap with the requested height according to
+    /// `strike_height_policy`. Bitmaps of the requested height are returned unchanged, as are
+    /// empty bitmaps under `Scale`, which have no aspect ratio to keep.
+    fn fit_strike_height(&self, glyph: glyph::Glyph) -> Result<glyph::Glyph, GlyphError> {
+        let height = self.font.height() as u32;
+        if glyph.height == height {
+            return Ok(glyph);
         }
+        return Ok(match self.strike_height_policy {
+            None => glyph,
+            Some(StrikeHeightPolicy::Exact) => 
+                return Err(GlyphError::StrikeHeightMismatch{height: glyph.height, expected_height: height}),
+            Some(StrikeHeightPolicy::Scale) if glyph.height == 0 || glyph.width == 0 => glyph,
+            Some(StrikeHeightPolicy::Scale) => {
+                let width = (glyph.width as f64 * height as f64 / glyph.height as f64).round() as u32;
+                glyph.scale(height, width)
+            }
+            Some(StrikeHeightPolicy::Pad) => {
+                let width = glyph.width;
+                glyph.resize_canvas(height, width, glyph::HAnchor::Left, glyph::VAnchor::Top)
+            }
+        });
     }
 
-    fn rasterize(&self, character: char) -> glyph::Glyph {
-        let glyph: ab_glyph::Glyph = self.font
-            .glyph_id(character)
+    /// Measures how much of each pixel of its canvas the outline of `glyph_id` covers, before
+    /// `rasterize` flattens it to one bit. The canvas is `cell_width` wide. Returns an error if the
+    /// canvas would have more than `MAX_GLYPH_PIXELS` pixels.
+    fn coverage(&self, glyph_id: GlyphId, cell_width: CellWidth) -> Result<Coverage, GlyphError> {
+        let glyph: ab_glyph::Glyph = glyph_id
             .with_scale_and_position(self.font.height(), point(0.0, 0.0));
 
-        let width = self.font.h_advance(glyph.id).ceil() as u32;
+        let outline_glyph = self.font.outline_glyph(glyph.clone());
+        // with CellWidth::Ink, the canvas starts at the left edge of the ink rather than at the
+        // glyph origin.
+        let (width, x_origin) = match (cell_width, &outline_glyph) {
+            (CellWidth::Ink, Some(og)) => {
+                let bounds = og.px_bounds();
+                ((bounds.max.x - bounds.min.x).ceil() as u32, bounds.min.x)
+            }
+            (CellWidth::Em, _) => {
+                let em = self.font.font.units_per_em().unwrap_or(1000.0) * self.font.h_scale_factor();
+                (em.ceil() as u32, 0.0)
+            }
+            _ => (self.font.h_advance(glyph.id).ceil() as u32, 0.0),
+        };
+        // px_bounds are whole pixels, so this is exact.
+        let x_offset = x_origin as i32;
         let height = self.font.height() as u32;
-        let byte_aligned_width = (8.0 * (width as f64 / 8.0).ceil()) as u32;
+        let too_large = || GlyphError::TooLarge{height, width, max_pixels: MAX_GLYPH_PIXELS};
+        width.div_ceil(8).checked_mul(8)
+            .and_then(|byte_aligned_width| byte_aligned_width.checked_mul(height))
+            .filter(|p| *p <= MAX_GLYPH_PIXELS)
+            .ok_or_else(too_large)?;
 
-        let mut data = bitvec![u8, Msb0; 0; (byte_aligned_width * height).try_into().unwrap()];
-        let mut pixel_perfect = true;
+        let mut values = vec![0.0; (width * height) as usize];
+        // coverage of pixels outside the canvas, to count the clipped ones once it is summed.
+        let mut outside: BTreeMap<(i32, i32), f32> = BTreeMap::new();
+        let mut clipping = Clipping::default();
+        let mut bearing_x = None;
+        let mut bearing_y = None;
         
-        if let Some(og) = self.font.outline_glyph(glyph) {
+        if let Some(og) = outline_glyph {
             let bounds = og.px_bounds();
-            og.draw( |x, y, v| {
-                if v != 1.0 && v != 0.0 {
-                    pixel_perfect = false;
+            bearing_x = Some(self.font.h_side_bearing(og.glyph().id).round() as i32);
+            bearing_y = Some(-bounds.min.y as i32);
+            // with supersampling, the outline is drawn `n` times larger and each `n` x `n` block
+            // of its pixels is averaged into one pixel of the canvas.
+            let n = self.supersample as i32;
+            let og = match n {
+                1 => og,
+                _ => match self.font.outline_glyph(glyph_id.with_scale_and_position(
+                    self.font.height() * n as f32, point(0.0, 0.0))) {
+                    Some(og) => og,
+                    None => return Ok(Coverage{width, height, values, pixel_perfect: true, clipping, bearing_x, bearing_y, x_offset}),
                 }
+            };
+            let bounds = og.px_bounds();
+            let weight = 1.0 / (n * n) as f32;
+            og.draw( |x, y, v| {
                 // Align this glyph's canvas with the font's baseline. 
                 // Warning: glyphs may extend above the font's ascent or below the font's descent
                 // -- they will be chopped off in this case. This is, in my opinion, an inherent
                 // hazard of smushing an OTF font into a strict monospace bitmap format.
-                let y_signed = (y as f32 + bounds.min.y + self.font.ascent()) as i32;
-                let x_signed = (x as f32 + bounds.min.x) as i32;
-
-                if y_signed < 0 || x_signed < 0 
-                    || y_signed >= height.try_into().unwrap() || x_signed >= width.try_into().unwrap() {
-                    eprintln!("While rasterizing {}: pixel ({}, {}) is out of bounds and will not be rendered",
-                        character, x_signed, y_signed);
-                }
+                let y_signed = ((y as f32 + bounds.min.y + self.font.ascent() * n as f32) as i32 
+                    + self.vertical_offset * n).div_euclid(n);
+                let x_signed = (x as f32 + bounds.min.x - x_origin * n as f32) as i32;
+                let x_signed = x_signed.div_euclid(n);
 
                 let y = y_signed as u32;
                 let x = x_signed as u32;
 
-                if x < width && y < height && v >= 0.5 {
-                    data.set((x as usize) + (y as usize) * (byte_aligned_width as usize), true);
+                if x < width && y < height {
+                    values[(x + y * width) as usize] += v * weight;
+                } else {
+                    *outside.entry((x_signed, y_signed)).or_insert(0.0) += v * weight;
                 }
             })
         }
 
+        for ((x, y), v) in outside.iter() {
+            if *v < self.coverage_threshold {
+                continue;
+            }
+            if *y < 0 {
+                clipping.above += 1;
+            } else if *y >= height as i32 {
+                clipping.below += 1;
+            } else if *x < 0 {
+                clipping.left += 1;
+            } else {
+                clipping.right += 1;
+            }
+        }
+        let pixel_perfect = values.iter().chain(outside.values()).all(|v| *v == 0.0 || *v == 1.0);
+
+        return Ok(Coverage{width, height, values, pixel_perfect, clipping, bearing_x, bearing_y, x_offset});
+    }
+
+    /// Measures how much of each pixel the outline of `character` covers, before it is flattened
+    /// to one bit. Returns `None` if the character would be rendered from an embedded bitmap, or
+    /// is a space and so always rendered blank.
+    pub fn coverage_char(&self, character: char) -> Result<Option<Coverage>, GlyphError> {
+        let parser = self.parser_for(character).unwrap_or(self);
+        let glyph_id = parser.font.glyph_id(character);
+        if is_blank_space(character) 
+            || parser.font.font.glyph_raster_image2(glyph_id, parser.font.height().ceil() as u16).is_some() {
+            return Ok(None);
+        }
+        return Ok(Some(parser.coverage(glyph_id, parser.cell_width)?));
+    }
+
+    /// Rasterizes the outline of `glyph_id` on a canvas `cell_width` wide, setting each pixel at
+    /// least `coverage_threshold` covered, after diffusing the error of earlier pixels if
+    /// `raster_mode` is `Dither`. Returns an error if the canvas would have more than
+    /// `MAX_GLYPH_PIXELS` pixels.
+    fn rasterize(&self, glyph_id: GlyphId, cell_width: CellWidth, grapheme: String, description: &str) 
+        -> Result<glyph::Glyph, GlyphError> {
+        let Coverage{width, height, mut values, pixel_perfect, clipping, bearing_x, bearing_y, x_offset} = self.coverage(glyph_id, cell_width)?;
+        let byte_aligned_width = width.div_ceil(8) * 8;
+        let mut data = bitvec![u8, Msb0; 0; (byte_aligned_width * height) as usize];
+        let row_length = width as usize;
+        for i in 0..values.len() {
+            let v = values[i];
+            let set = v >= self.coverage_threshold;
+            let (x, y) = (i % row_length, i / row_length);
+            if set {
+                data.set(x + y * (byte_aligned_width as usize), true);
+            }
+            if self.raster_mode == RasterMode::Dither {
+                let error = v - if set {1.0} else {0.0};
+                let mut diffuse = |x: usize, y: usize, weight: f32| {
+                    if x < row_length && y < height as usize {
+                        values[x + y * row_length] += error * weight;
+                    }
+                };
+                diffuse(x + 1, y, 7.0 / 16.0);
+                if x > 0 {
+                    diffuse(x - 1, y + 1, 3.0 / 16.0);
+                }
+                diffuse(x, y + 1, 5.0 / 16.0);
+                diffuse(x + 1, y + 1, 1.0 / 16.0);
+            }
+        }
+
         if !pixel_perfect {
-            eprintln!("While rasterizing {}: the glyph outline was not pixel-perfect.", character);
+            self.flattened_glyphs.set(self.flattened_glyphs.get() + 1);
+            log::debug!("While rasterizing {}: the glyph outline was not pixel-perfect.", description);
+        }
+        if clipping.total() > 0 {
+            self.clipped_glyphs.borrow_mut().push(ClippedGlyph{description: description.to_string(), clipping});
         }
 
         let data = data.into_vec();
-        let grapheme = character.to_string();
+        let ppem = self.ppem();
 
-        return glyph::Glyph{ height, width, data, grapheme };
+        // the font's ascent (moved by `vertical_offset`) puts the baseline this far above the bottom.
+        let y_offset = self.ascent() as i32 + self.vertical_offset - height as i32;
+        return Ok(glyph::Glyph{ height, width, data, grapheme, bearing_x, bearing_y, 
+            x_offset: Some(x_offset), y_offset: Some(y_offset), ppem });
         
     }
 }
+
+/// Whether `character` is a space that is always rendered as a blank cell, whatever the font's
+/// glyph for it looks like. This is every Unicode whitespace character except U+1680 OGHAM SPACE
+/// MARK, which is drawn as a visible stroke.
+pub fn is_blank_space(character: char) -> bool {
+    return character.is_whitespace() && character != '\u{1680}';
+}
+
+/// Checks whether the outline of `character` lies entirely within the canvas that `rasterize`
+/// would draw it on: the font's height, by the glyph's advance width, aligned to the baseline.
+fn ink_fits_canvas<F: Font, SF: ScaleFont<F>>(font: &SF, character: char) -> bool {
+    let glyph: ab_glyph::Glyph = font
+        .glyph_id(character)
+        .with_scale_and_position(font.height(), point(0.0, 0.0));
+    let width = font.h_advance(glyph.id).ceil() as i32;
+    let height = font.height() as i32;
+    return match font.outline_glyph(glyph) {
+        None => true,
+        Some(og) => {
+            // use the same pixel arithmetic as `rasterize`, so that "fits" means "no pixel is
+            // reported out of bounds".
+            let bounds = og.px_bounds();
+            let (min_x, max_x) = (bounds.min.x as i32, (bounds.max.x - 1.0) as i32);
+            let min_y = (bounds.min.y + font.ascent()) as i32;
+            let max_y = (bounds.max.y - 1.0 + font.ascent()) as i32;
+            min_x >= 0 && max_x < width && min_y >= 0 && max_y < height
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn demo_font(height: u32) -> TtfParser {
+        return TtfParser::from_bytes(include_bytes!("../fixtures/demo.ttf").to_vec(), height).unwrap();
+    }
+
+    #[test]
+    fn fallback_with_same_cell_is_accepted() {
+        let mut ttf_parser = demo_font(8);
+        ttf_parser.add_fallback(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/demo.ttf"))).unwrap();
+        assert!(ttf_parser.check_fallback_cells().is_ok());
+    }
+
+    #[test]
+    fn fallback_with_different_cell_is_rejected() {
+        let mut ttf_parser = demo_font(8);
+        ttf_parser.fallbacks.push(demo_font(16));
+        assert!(matches!(ttf_parser.check_fallback_cells(), 
+            Err(TtfParserError::FallbackCellSize{fallback: 1, expected_height: 8, ..})));
+    }
+
+    #[test]
+    fn demo_font_renders_its_one_letter() {
+        let ttf_parser = demo_font(16);
+        assert!(ttf_parser.is_defined('A'));
+        assert!(!ttf_parser.is_defined('B'));
+        let a = ttf_parser.render_char('A').unwrap();
+        assert_eq!((a.height, a.grapheme.as_str()), (16, "A"));
+        assert!(a.rows().any(|row| row.any()));
+        // undefined characters get the `.notdef` box, in the same cell height.
+        let b = ttf_parser.render_char('B').unwrap();
+        assert_eq!(b.height, 16);
+        assert_ne!(a.data, b.data);
+    }
+
+    #[test]
+    fn strike_height_policy_reconciles_taller_strike() {
+        // a 16 px strike bitmap, for a font requested at 8 px.
+        let strike = glyph::Glyph::from_ascii_art(&["#..#"; 16], String::from("x"), 16).unwrap();
+        let mut ttf_parser = demo_font(8);
+        assert_eq!(ttf_parser.fit_strike_height(strike.clone()).unwrap().height, 16);
+
+        ttf_parser.set_strike_height_policy(Some(StrikeHeightPolicy::Exact));
+        assert!(matches!(ttf_parser.fit_strike_height(strike.clone()), 
+            Err(GlyphError::StrikeHeightMismatch{height: 16, expected_height: 8})));
+
+        ttf_parser.set_strike_height_policy(Some(StrikeHeightPolicy::Scale));
+        let scaled = ttf_parser.fit_strike_height(strike.clone()).unwrap();
+        assert_eq!((scaled.height, scaled.width), (8, 2));
+        assert_eq!(scaled.to_ascii_art(), ["#."; 8].join("\n"));
+
+        ttf_parser.set_strike_height_policy(Some(StrikeHeightPolicy::Pad));
+        let cropped = ttf_parser.fit_strike_height(strike).unwrap();
+        assert_eq!((cropped.height, cropped.width), (8, 4));
+        assert_eq!(cropped.to_ascii_art(), ["#..#"; 8].join("\n"));
+    }
+
+    #[test]
+    fn empty_strike_is_not_scaled() {
+        let mut ttf_parser = demo_font(8);
+        ttf_parser.set_strike_height_policy(Some(StrikeHeightPolicy::Scale));
+        for (height, width) in [(0, 4), (16, 0)] {
+            let strike = glyph::Glyph::blank(height, width, String::from(" "), 16);
+            let kept = ttf_parser.fit_strike_height(strike).unwrap();
+            assert_eq!((kept.height, kept.width), (height, width));
+        }
+    }
+
+    #[test]
+    fn strike_of_requested_height_is_kept() {
+        let strike = glyph::Glyph::from_ascii_art(&["#."; 8], String::from("x"), 8).unwrap();
+        let mut ttf_parser = demo_font(8);
+        ttf_parser.set_strike_height_policy(Some(StrikeHeightPolicy::Exact));
+        assert_eq!(ttf_parser.fit_strike_height(strike.clone()).unwrap().data, strike.data);
+    }
+
+    #[test]
+    fn cell_width_modes_size_a_narrow_glyph_differently() {
+        let width = |cell_width| {
+            let mut ttf_parser = demo_font(16);
+            ttf_parser.set_cell_width(cell_width);
+            // the `.notdef` box is inked over 500 of its 600-unit advance, inside a 1000-unit em.
+            return ttf_parser.render_glyph_id(0).unwrap().width;
+        };
+        assert_eq!([width(CellWidth::Ink), width(CellWidth::Advance), width(CellWidth::Em)], [6, 7, 12]);
+    }
+
+    #[test]
+    fn oversized_canvas_is_too_large() {
+        // 8192 x about 3500 px is over `MAX_GLYPH_PIXELS`; 200000 x about 87000 px overflows a u32.
+        for height in [8192, 200_000] {
+            let result = demo_font(height).render_char('A');
+            assert!(matches!(result, Err(GlyphError::TooLarge{max_pixels: MAX_GLYPH_PIXELS, height: h, ..}) if h == height));
+        }
+        assert!(demo_font(1024).render_char('A').is_ok());
+    }
+
+    /// The demo font with its `cmap` segment for 'A' moved to U+0020, so that space maps to the
+    /// outline of 'A', and that glyph's `hmtx` advance set to `advance` font units.
+    fn demo_font_with_space(height: u32, advance: u16) -> TtfParser {
+        let mut font_data = include_bytes!("../fixtures/demo.ttf").to_vec();
+        // the format 4 subtable starts at byte 268: end code, start code, and id delta of segment 0.
+        font_data[282..284].copy_from_slice(&0x20u16.to_be_bytes());
+        font_data[288..290].copy_from_slice(&0x20u16.to_be_bytes());
+        font_data[292..294].copy_from_slice(&(1u16.wrapping_sub(0x20)).to_be_bytes());
+        // `hmtx` starts at byte 248, with the metrics of `.notdef` before those of 'A'.
+        font_data[252..254].copy_from_slice(&advance.to_be_bytes());
+        return TtfParser::from_bytes(font_data, height).unwrap();
+    }
+
+    /// The demo font with its `cmap` segment for 'A' moved to U+FFFD, so that the replacement
+    /// character draws the 'A' outline.
+    fn demo_font_with_fffd(height: u32) -> TtfParser {
+        let mut font_data = include_bytes!("../fixtures/demo.ttf").to_vec();
+        font_data[282..284].copy_from_slice(&0xfffdu16.to_be_bytes());
+        font_data[288..290].copy_from_slice(&0xfffdu16.to_be_bytes());
+        font_data[292..294].copy_from_slice(&(1u16.wrapping_sub(0xfffd)).to_be_bytes());
+        return TtfParser::from_bytes(font_data, height).unwrap();
+    }
+
+    #[test]
+    fn undefined_characters_take_the_fffd_glyph() {
+        let mut ttf_parser = demo_font_with_fffd(16);
+        ttf_parser.set_fallback_to_fffd(true);
+        let replacement = ttf_parser.render_char('\u{fffd}').unwrap();
+        let glyph = ttf_parser.render_char('B').unwrap();
+        assert_eq!(glyph.grapheme, "B");
+        assert_eq!((glyph.height, glyph.width), (replacement.height, replacement.width));
+        assert_eq!(glyph.data, replacement.data);
+        // without the option, 'B' gets `.notdef`, which looks nothing like 'A'.
+        ttf_parser.set_fallback_to_fffd(false);
+        assert_ne!(ttf_parser.render_char('B').unwrap().data, replacement.data);
+    }
+
+    #[test]
+    fn undefined_characters_get_a_box_without_an_fffd_glyph() {
+        let mut ttf_parser = demo_font(16);
+        ttf_parser.set_fallback_to_fffd(true);
+        let space = ttf_parser.space_cell().unwrap();
+        let glyph = ttf_parser.render_char('B').unwrap();
+        let outline_box = glyph::Glyph::outline_box(space.height, space.width, String::from("B"), space.ppem);
+        assert_eq!((glyph.height, glyph.width), (space.height, space.width));
+        assert_eq!(glyph.to_ascii_art(), outline_box.to_ascii_art());
+    }
+
+    #[test]
+    fn zero_advances_are_detected() {
+        assert!(demo_font(16).has_advance_metrics());
+        let mut font_data = include_bytes!("../fixtures/demo.ttf").to_vec();
+        // zero the advances of `.notdef` and 'A' in `hmtx`, which starts at byte 248.
+        font_data[248..250].fill(0);
+        font_data[252..254].fill(0);
+        let mut ttf_parser = TtfParser::from_bytes(font_data, 16).unwrap();
+        assert!(!ttf_parser.has_advance_metrics());
+        assert_eq!(ttf_parser.render_char('A').unwrap().width, 0);
+        // the em width that `convert` falls back to still gives 'A' its ink.
+        ttf_parser.set_cell_width(CellWidth::Em);
+        assert!(ttf_parser.render_char('A').unwrap().data.iter().any(|b| *b != 0));
+    }
+
+    #[test]
+    fn space_width_is_the_space_advance() {
+        assert!(matches!(demo_font(16).space_width(), Err(GlyphError::NoSpaceGlyph)));
+        let ttf_parser = demo_font_with_space(16, 1000);
+        assert!(ttf_parser.is_defined(' '));
+        assert_eq!(ttf_parser.space_width().unwrap(), 12);
+        // the advance, not the ink, sets the width.
+        assert_eq!(demo_font_with_space(16, 1500).space_width().unwrap(), 17);
+    }
+
+    #[test]
+    fn bearings_match_ab_glyph_metrics() {
+        let font = FontVec::try_from_vec_and_index(include_bytes!("../fixtures/demo.ttf").to_vec(), 0).unwrap();
+        let scaled_font = font.as_scaled(PxScale::from(16.0));
+        let glyph_id = scaled_font.glyph_id('A');
+        let og = scaled_font.outline_glyph(glyph_id.with_scale_and_position(16.0, point(0.0, 0.0))).unwrap();
+
+        let glyph = demo_font(16).render_char('A').unwrap();
+        assert_eq!(glyph.bearing_x, Some(scaled_font.h_side_bearing(glyph_id).round() as i32));
+        assert_eq!(glyph.bearing_y, Some(-og.px_bounds().min.y as i32));
+        // the top of the ink is above the baseline.
+        assert!(glyph.bearing_y.unwrap() > 0);
+    }
+
+    #[test]
+    fn spaces_render_as_full_size_blank_cells() {
+        // U+0020 maps to the inked 'A' outline; the others fall back to the space cell.
+        let ttf_parser = demo_font_with_space(16, 1000);
+        for character in [' ', '\u{3000}', '\u{a0}'] {
+            let glyph = ttf_parser.render_char(character).unwrap();
+            assert_eq!((glyph.height, glyph.width), (16, 12), "{:?}", character);
+            assert!(glyph.data.iter().all(|b| *b == 0), "{:?}", character);
+        }
+        // the ogham space mark is a visible stroke, so it is not blanked: here it gets `.notdef`.
+        let ogham = ttf_parser.render_char('\u{1680}').unwrap();
+        assert!(ogham.data.iter().any(|b| *b != 0));
+    }
+
+    #[test]
+    fn composing_glyphs_of_different_widths() {
+        // with ink-width cells, 'A' is 7 px wide and the `.notdef` drawn for 'B' is 6 px wide.
+        let mut ttf_parser = demo_font(16);
+        ttf_parser.set_cell_width(CellWidth::Ink);
+        let composed = ttf_parser.render_string("AB").unwrap();
+        assert_eq!((composed.width, composed.grapheme.as_str()), (7, "AB"));
+        ttf_parser.set_strict_compose(true);
+        let result = ttf_parser.render_string("AB");
+        assert!(matches!(result, Err(GlyphError::Compose{grapheme, inner}) 
+            if grapheme == "AB" && matches!(*inner, GlyphError::WrongDimensions{width: 7, expected_width: 6, ..})));
+    }
+}
@@ -1,71 +1,401 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
 
 use crate::errors::UnicodeTableError;
 
 use pest::Parser;
 use pest_derive::Parser;
 
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+/// The number of glyph indices a Unicode table entry can be pinned to. The console's Unicode map
+/// stores glyph positions in 16 bits, so later glyphs could never be mapped anyway.
+const MAX_PINNED_GLYPH_COUNT: u32 = 0x10000;
+
 #[derive(Parser)]
 #[grammar = "unicode_table_grammar.pest"]
 pub struct UnicodeTableParser;
 
 pub struct UnicodeTable {
     pub data: Vec<Vec<String>>,
+    /// The `@name`, `@author`, and `@version` directives of the table file, if it was read from one.
+    pub meta: UnicodeTableMeta,
+    /// The number of leading entries whose glyph indices are fixed by `@NNN:` pins: every entry up
+    /// to and including the last pinned one, gaps included. Zero if nothing is pinned.
+    pub pinned_len: usize,
 }
 
-impl UnicodeTable {
-    pub fn from_file(path: &Path, glyph_count: Option<u32>) -> Result<Self, UnicodeTableError> {
-        let unparsed_file = fs::read_to_string(path)?;
+/// Descriptive metadata from the `@key value` lines at the top of a Unicode table file. PSF2 has
+/// nowhere to store it, but `report` and the C header output echo it.
+#[derive(Debug, Clone, Default)]
+pub struct UnicodeTableMeta {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub version: Option<String>,
+}
+
+impl UnicodeTableMeta {
+    /// The fields that are set, as `(label, value)` pairs, in the order name, author, version.
+    pub fn fields(&self) -> Vec<(&'static str, &str)> {
+        return [("Name", &self.name), ("Author", &self.author), ("Version", &self.version)].into_iter()
+            .filter_map(|(label, value)| value.as_deref().map(|v| (label, v)))
+            .collect();
+    }
+}
+
+/// One line (equivalence group) of a Unicode table file, as read by `TableFileReader`.
+struct TableFileRow {
+    graphemes: Vec<String>,
+    pin: Option<u32>,
+    /// Which file the line came from, numbered in the order the files were read.
+    file: usize,
+}
+
+/// Reads the lines of a Unicode table file, and of the files it includes, in order.
+#[derive(Default)]
+struct TableFileReader {
+    rows: Vec<TableFileRow>,
+    /// The canonical paths of the files being read, each included by the one before it.
+    include_stack: Vec<PathBuf>,
+    /// The path of each file read, indexed by `TableFileRow::file`.
+    files: Vec<PathBuf>,
+    /// The metadata directives read so far. Each key keeps its first value, so the including
+    /// file's directives win over those of the files it includes.
+    meta: UnicodeTableMeta,
+}
+
+impl TableFileReader {
+    /// Reads the lines of the file at `path`, replacing each `#include "path"` line with the lines
+    /// of the included file. Included paths are relative to the including file's directory.
+    fn read(&mut self, path: &Path) -> Result<(), UnicodeTableError> {
+        let canonical_path = fs::canonicalize(path)?;
+        if self.include_stack.contains(&canonical_path) {
+            return Err(UnicodeTableError::IncludeCycle{path: path.to_path_buf()});
+        }
+        self.include_stack.push(canonical_path);
+        let file_index = self.files.len();
+        self.files.push(path.to_path_buf());
+
+        let unparsed_file = UnicodeTable::read_utf8(path)?;
         let file = UnicodeTableParser::parse(Rule::file, &unparsed_file)?
             .next().unwrap(); // get and unwrap the 'file' rule; never fails
         
-        let mut data: Vec<Vec<String>> = vec![];
         for row in file.into_inner() {
-            if row.as_rule() == Rule::equiv_graphemes_set {
+            if row.as_rule() == Rule::meta_line {
+                let mut meta = row.into_inner().next().unwrap().into_inner(); // the 'meta' rule
+                let key = meta.next().unwrap().as_str();
+                let value = meta.next().unwrap().as_str().trim().to_string();
+                let field = match key {
+                    "name" => &mut self.meta.name,
+                    "author" => &mut self.meta.author,
+                    "version" => &mut self.meta.version,
+                    _ => {
+                        log::warn!("Ignoring unknown directive @{} in {}.", key, path.display());
+                        continue;
+                    }
+                };
+                field.get_or_insert(value);
+            } else if row.as_rule() == Rule::equiv_graphemes_set {
                 let mut data_equiv_graphemes_set: Vec<String> = vec![];
+                let mut pin: Option<u32> = None;
                 for entry in row.into_inner() {
-                    if entry.as_rule() == Rule::grapheme {
+                    if entry.as_rule() == Rule::glyph_index {
+                        let digits = entry.into_inner().next().unwrap().as_str();
+                        pin = Some(digits.parse::<u32>()?);
+                    } else if entry.as_rule() == Rule::grapheme {
                         let mut data_grapheme: String = String::new();
                         for codepoint in entry.into_inner() {
-                            let value = u32::from_str_radix(
-                                codepoint.into_inner().nth(1)
-                                .expect("Unicode 'U+' prefix without codepoint found in Unicode table").as_str(),
-                                16)?;
-                            let character = char::from_u32(value);
-                            match character {
-                                None => return Err(UnicodeTableError::InvalidCodepoint{codepoint: value}),
-                                Some(c) => data_grapheme.push(c),
-                            }
+                            let hex_digits = codepoint.into_inner().nth(1)
+                                .expect("Unicode 'U+' prefix without codepoint found in Unicode table").as_str();
+                            data_grapheme.push(parse_codepoint(hex_digits)?);
                        }
                         data_equiv_graphemes_set.push(data_grapheme);
                     }
                 }
                 /* list single-character graphemes first */
                 data_equiv_graphemes_set.sort_by_key(|str| str.chars().count());
-                data.push(data_equiv_graphemes_set);
+                self.rows.push(TableFileRow{graphemes: data_equiv_graphemes_set, pin, file: file_index});
+            } else if row.as_rule() == Rule::codepoint_range {
+                let mut ends = row.into_inner().map(|codepoint| codepoint.into_inner().nth(1)
+                    .expect("Unicode 'U+' prefix without codepoint found in Unicode table").as_str());
+                let start = u32::from(parse_codepoint(ends.next().unwrap())?);
+                let end = u32::from(parse_codepoint(ends.next().unwrap())?);
+                if end < start {
+                    return Err(UnicodeTableError::InvertedRange{start, end});
+                }
+                // surrogate codepoints inside the range are not characters, so they are skipped.
+                for c in (start..=end).filter_map(char::from_u32) {
+                    self.rows.push(TableFileRow{graphemes: vec![c.to_string()], pin: None, file: file_index});
+                }
+            } else if row.as_rule() == Rule::include_line {
+                let include_path = row.into_inner().next().unwrap() // the 'include' rule
+                    .into_inner().next().unwrap().as_str(); // the 'include_path' rule
+                self.read(&path.parent().unwrap_or(Path::new("")).join(include_path))?;
             }
         }
 
+        self.include_stack.pop();
+        return Ok(());
+    }
+
+    /// Drops each grapheme that an earlier line from a different file already lists, with a
+    /// warning, and any lines left empty. Duplicates within one file are left for
+    /// `merge_overlapping_groups` to handle.
+    fn drop_included_duplicates(&mut self) {
+        let mut owner: HashMap<String, usize> = HashMap::new();
+        for row in self.rows.iter_mut() {
+            row.graphemes.retain(|grapheme| match owner.get(grapheme) {
+                Some(file) if *file != row.file => {
+                    log::warn!("Ignoring {:?} in {}: {} already lists it.", 
+                        grapheme, self.files[row.file].display(), self.files[*file].display());
+                    false
+                }
+                _ => {
+                    owner.insert(grapheme.clone(), row.file);
+                    true
+                }
+            });
+        }
+        self.rows.retain(|row| !row.graphemes.is_empty());
+    }
+}
+
+impl UnicodeTable {
+    /// Reads a Unicode table file. A line `U+XXXX..U+YYYY` stands for one line per codepoint in
+    /// the range, in order. If two lines (equivalence groups) share a grapheme, they are
+    /// merged into one group if `merge_equiv` is set; otherwise an error is returned. Lines
+    /// prefixed with `@NNN:` are pinned to glyph index NNN (see `pin_groups`). A line
+    /// `#include "path"` stands for the lines of another table file; graphemes it repeats from
+    /// another file are ignored. Lines `@name`, `@author`, and `@version` at the top of a file set
+    /// the table's metadata; other `@` directives are ignored with a warning. If `glyph_count` is
+    /// given, at most that many groups are kept.
+    pub fn from_file(path: &Path, glyph_count: Option<u32>, merge_equiv: bool) -> Result<Self, UnicodeTableError> {
+        let mut reader = TableFileReader::default();
+        reader.read(path)?;
+        reader.drop_included_duplicates();
+        let (data, pins): (Vec<Vec<String>>, Vec<Option<u32>>) = reader.rows.into_iter()
+            .map(|row| (row.graphemes, row.pin))
+            .unzip();
+        let mut table = Self::from_data(data, &pins, glyph_count, merge_equiv)?;
+        table.meta = reader.meta;
+        return Ok(table);
+    }
+
+    /// Reads a Unicode table from a console-tools `.uni` file, as written by `psfgettable`. Overlap
+    /// handling and `glyph_count` work as in `from_file`.
+    pub fn from_unimap_file(path: &Path, glyph_count: Option<u32>, merge_equiv: bool) -> Result<Self, UnicodeTableError> {
+        let unparsed_file = Self::read_utf8(path)?;
+        let data = crate::unimap::parse_unimap(&unparsed_file)?;
+        return Self::from_data(data, &[], glyph_count, merge_equiv);
+    }
+
+    /// Builds a table from parsed equivalence groups, merging overlapping groups (or returning an
+    /// error), moving groups to the glyph indices they are pinned to in `pins` (indexed like
+    /// `data`; missing entries are unpinned), and keeping at most `glyph_count` groups.
+    fn from_data(data: Vec<Vec<String>>, pins: &[Option<u32>], glyph_count: Option<u32>, merge_equiv: bool) 
+        -> Result<Self, UnicodeTableError> {
+        let mut table = UnicodeTable{data, meta: UnicodeTableMeta::default(), pinned_len: 0};
+        let sources = table.merge_overlapping_groups(merge_equiv)?;
+        if pins.iter().any(Option::is_some) {
+            let merged_pins = sources.iter()
+                .map(|group_sources| {
+                    let mut group_pins = group_sources.iter().filter_map(|i| pins.get(*i).copied().flatten()).unique();
+                    match (group_pins.next(), group_pins.next()) {
+                        (Some(first), Some(second)) => Err(UnicodeTableError::ConflictingGlyphIndices{first, second}),
+                        (pin, _) => Ok(pin),
+                    }
+                })
+                .collect::<Result<Vec<Option<u32>>, UnicodeTableError>>()?;
+            table.pin_groups(&merged_pins, glyph_count)?;
+        }
         if let Some(gc) = glyph_count {
-            data.truncate(gc as usize);
+            table.data.truncate(gc as usize);
+        }
+        return Ok(table);
+    }
+
+    /// Finds equivalence groups that share a grapheme, and unions them (transitively) into a
+    /// single group in place of the earliest one. Returns an error instead if `merge` is not set.
+    /// Returns the original indices of the groups that went into each resulting group.
+    fn merge_overlapping_groups(&mut self, merge: bool) -> Result<Vec<Vec<usize>>, UnicodeTableError> {
+        // union-find over group indices: `parent[i] == i` for the root of each merged group, and
+        // the root is always the group that appears first in the table.
+        let mut parent: Vec<usize> = (0..self.data.len()).collect();
+        fn find(parent: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            parent[i] = root;
+            return root;
+        }
+
+        let mut owner: HashMap<&str, usize> = HashMap::new();
+        for (i, group) in self.data.iter().enumerate() {
+            for grapheme in group.iter() {
+                let Some(&j) = owner.get(grapheme.as_str()) else {
+                    owner.insert(grapheme, i);
+                    continue;
+                };
+                if !merge {
+                    return Err(UnicodeTableError::OverlappingGroups{
+                        grapheme: grapheme.clone(), first_index: j, second_index: i,
+                    });
+                }
+                let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+                parent[std::cmp::max(root_i, root_j)] = std::cmp::min(root_i, root_j);
+            }
         }
-        return Ok(UnicodeTable{data});
+
+        let mut merged: Vec<Vec<String>> = vec![];
+        let mut merged_index: HashMap<usize, usize> = HashMap::new();
+        let mut merged_sources: Vec<Vec<usize>> = vec![];
+        for (i, group) in std::mem::take(&mut self.data).into_iter().enumerate() {
+            let root = find(&mut parent, i);
+            let index = *merged_index.entry(root).or_insert_with(|| {
+                merged.push(vec![]);
+                merged_sources.push(vec![]);
+                merged.len() - 1
+            });
+            merged_sources[index].push(i);
+            for grapheme in group.into_iter() {
+                if !merged[index].contains(&grapheme) {
+                    merged[index].push(grapheme);
+                }
+            }
+        }
+
+        for (group, sources) in merged.iter_mut().zip(merged_sources.iter()) {
+            if sources.len() > 1 {
+                log::info!("Merged overlapping Unicode table entries {:?} into one glyph: {:?}", sources, group);
+            }
+            /* list single-character graphemes first */
+            group.sort_by_key(|str| str.chars().count());
+        }
+        self.data = merged;
+        return Ok(merged_sources);
     }
 
-    pub fn write(self) -> Vec<u8> {
+    /// Moves each group with a pin in `pins` to that glyph index, and fills the remaining indices
+    /// with the unpinned groups, in order. Indices that are still unused below the highest pinned
+    /// index are left as empty groups, which render as blank glyphs, and `pinned_len` is set to
+    /// the highest pinned index plus one. Returns an error if two groups are pinned to the same
+    /// index, or if a group is pinned beyond `glyph_count` (if given) or `MAX_PINNED_GLYPH_COUNT`.
+    fn pin_groups(&mut self, pins: &[Option<u32>], glyph_count: Option<u32>) -> Result<(), UnicodeTableError> {
+        let limit = glyph_count.map_or(MAX_PINNED_GLYPH_COUNT, |gc| std::cmp::min(gc, MAX_PINNED_GLYPH_COUNT));
+        if let Some(index) = pins.iter().flatten().find(|index| **index >= limit) {
+            return Err(UnicodeTableError::GlyphIndexOutOfRange{index: *index, limit});
+        }
+        let mut slots: Vec<Option<Vec<String>>> = vec![];
+        let mut unpinned: Vec<Vec<String>> = vec![];
+        for (group, pin) in std::mem::take(&mut self.data).into_iter().zip(pins.iter()) {
+            let Some(index) = pin.map(|p| p as usize) else {
+                unpinned.push(group);
+                continue;
+            };
+            if slots.len() <= index {
+                slots.resize(index + 1, None);
+            }
+            if slots[index].is_some() {
+                return Err(UnicodeTableError::GlyphIndexCollision{index: index as u32});
+            }
+            slots[index] = Some(group);
+        }
+
+        let mut unpinned = unpinned.into_iter();
+        for slot in slots.iter_mut().filter(|slot| slot.is_none()) {
+            *slot = unpinned.next();
+        }
+        self.pinned_len = slots.len();
+        self.data = slots.into_iter().map(Option::unwrap_or_default).chain(unpinned).collect();
+        return Ok(());
+    }
+
+    /// Creates a table where each of `characters` is its own equivalence group.
+    pub fn from_chars(characters: impl IntoIterator<Item = char>) -> Self {
+        return Self{data: characters.into_iter().map(|c| vec![c.to_string()]).collect(), meta: UnicodeTableMeta::default(), pinned_len: 0};
+    }
+
+    /// Creates the table implied by a font without one, where glyph `i` represents `U+i`. Glyphs
+    /// at surrogate codepoints, which are not characters, get empty entries.
+    pub fn identity(glyph_count: u32) -> Self {
+        return Self{data: (0..glyph_count)
+            .map(|i| char::from_u32(i).map(|c| vec![c.to_string()]).unwrap_or_default())
+            .collect(), meta: UnicodeTableMeta::default(), pinned_len: 0};
+    }
+
+    /// Removes graphemes for which `keep` returns false, and any groups left empty.
+    pub fn retain_graphemes(&mut self, keep: impl Fn(&str) -> bool) {
+        for group in self.data.iter_mut() {
+            group.retain(|grapheme| keep(grapheme));
+        }
+        self.data.retain(|group| !group.is_empty());
+    }
+
+    /// Sorts the entries by their primary codepoint, the first codepoint of their first grapheme,
+    /// keeping equal entries in order and moving empty entries to the end. Returns the original
+    /// index of each entry in its new position, so that glyphs can be reordered to match.
+    pub fn sort_by_codepoint(&mut self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.data.len()).collect();
+        order.sort_by_key(|i| self.data[*i].first().and_then(|g| g.chars().next()).map_or(u32::MAX, u32::from));
+        let mut data: Vec<Option<Vec<String>>> = std::mem::take(&mut self.data).into_iter().map(Some).collect();
+        self.data = order.iter().map(|i| data[*i].take().unwrap()).collect();
+        return order;
+    }
+
+    /// Writes the table in the console-tools `.uni` text format read by `psfaddtable` and
+    /// `setfont -u`: one line per glyph, with the glyph index followed by each of its graphemes.
+    /// Codepoints in a multi-codepoint sequence are joined with commas. Glyphs without graphemes,
+    /// such as the gaps left by pinned entries, get a bare index line, since the format needs
+    /// every index up to the last one listed.
+    pub fn to_unimap(&self) -> String {
+        let mut unimap = String::from("# Unicode mapping table generated by otf2psf\n");
+        for (i, equivalent_graphemes_list) in self.data.iter().enumerate() {
+            if equivalent_graphemes_list.is_empty() {
+                unimap.push_str(&format!("0x{:03x}\n", i));
+                continue;
+            }
+            unimap.push_str(&format!("0x{:03x}\t", i));
+            let graphemes: Vec<String> = equivalent_graphemes_list.iter()
+                .map(|g| g.chars().map(|c| format!("U+{:04x}", u32::from(c))).collect::<Vec<_>>().join(","))
+                .collect();
+            unimap.push_str(&graphemes.join(" "));
+            unimap.push('\n');
+        }
+        return unimap;
+    }
+
+    /// The number of distinct codepoints used anywhere in the table.
+    pub fn codepoint_count(&self) -> usize {
+        let mut codepoints: Vec<char> = self.data.iter().flatten().flat_map(|g| g.chars()).collect();
+        codepoints.sort();
+        codepoints.dedup();
+        return codepoints.len();
+    }
+
+    /// Reads a Unicode table file as UTF-8 text, skipping a leading byte order mark if present.
+    fn read_utf8(path: &Path) -> Result<String, UnicodeTableError> {
+        let bytes = fs::read(path)?;
+        let bom_length = if bytes.starts_with(UTF8_BOM) {UTF8_BOM.len()} else {0};
+        return String::from_utf8(bytes[bom_length..].to_vec())
+            .map_err(|e| UnicodeTableError::InvalidUtf8{offset: bom_length + e.utf8_error().valid_up_to()});
+    }
+
+    pub fn write(&self) -> Vec<u8> {
        let ss: u8 = 0xfe; // start of sequence, for multi-char graphemes
        let term: u8 = 0xff; // terminates each list of equivalent graphemes
 
        let mut unicode_table: Vec<u8> = vec![];
 
-       for equivalent_graphemes_list in self.data.into_iter() {
+       for equivalent_graphemes_list in self.data.iter() {
             /* In a list of equivalent graphemes, single-character graphemes are listed first, 
              * followed by multi-character graphemes (with ss before each grapheme), 
              * followed by term to terminate the list. Since we have already sorted equivalent
              * graphemes by length when constructing the Unicode table, we don't have to re-sort
              * them now. */
-            for grapheme in equivalent_graphemes_list.into_iter() {
+            for grapheme in equivalent_graphemes_list.iter() {
                 if grapheme.chars().count() == 1 {
                     unicode_table.extend(grapheme.as_bytes().to_vec());
                 } else {
@@ -78,3 +408,134 @@ impl UnicodeTable {
        return unicode_table;
     }
 }
+
+/// Parses a codepoint such as `U+0041`, or a range such as `U+0041..U+005A`, to the characters it
+/// stands for. Surrogate codepoints inside a range are skipped.
+pub fn parse_codepoint_range(text: &str) -> Result<Vec<char>, UnicodeTableError> {
+    let parse = |codepoint: &str| parse_codepoint(codepoint.trim().trim_start_matches(['U', 'u']).trim_start_matches('+'));
+    let Some((start, end)) = text.split_once("..") else {
+        return Ok(vec![parse(text)?]);
+    };
+    let (start, end) = (u32::from(parse(start)?), u32::from(parse(end)?));
+    if end < start {
+        return Err(UnicodeTableError::InvertedRange{start, end});
+    }
+    return Ok((start..=end).filter_map(char::from_u32).collect());
+}
+
+/// Converts the hex digits of a `U+` codepoint to a character. Values above U+10FFFF, surrogates,
+/// and values too long to fit in a `u32` are all reported as `InvalidCodepoint`.
+pub fn parse_codepoint(hex_digits: &str) -> Result<char, UnicodeTableError> {
+    return u32::from_str_radix(hex_digits, 16).ok()
+        .and_then(char::from_u32)
+        .ok_or(UnicodeTableError::InvalidCodepoint{codepoint: hex_digits.to_string()});
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(graphemes: &[&str]) -> Vec<Vec<String>> {
+        return graphemes.iter().map(|g| vec![g.to_string()]).collect();
+    }
+
+    /// Writes `contents` to a table file named `name` in the temporary directory, and reads it.
+    fn read_table_file(name: &str, contents: &[u8]) -> Result<UnicodeTable, UnicodeTableError> {
+        let path = std::env::temp_dir().join(format!("otf2psf-{}-{}.set", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        let table = UnicodeTable::from_file(&path, None, false);
+        fs::remove_file(&path).unwrap();
+        return table;
+    }
+
+    #[test]
+    fn pinned_groups_move_and_unpinned_fill_gaps() {
+        let data = groups(&["a", "b", "c", "d", "e"]);
+        let pins = [None, Some(4), None, Some(1), None];
+        let table = UnicodeTable::from_data(data, &pins, None, false).unwrap();
+        assert_eq!(table.data, groups(&["a", "d", "c", "e", "b"]));
+
+        // a pin past the unpinned groups leaves blank glyphs before it.
+        let table = UnicodeTable::from_data(groups(&["a", "b"]), &[Some(3), None], None, false).unwrap();
+        assert_eq!(table.data, [vec!["b".to_string()], vec![], vec![], vec!["a".to_string()]]);
+    }
+
+    #[test]
+    fn pins_beyond_glyph_count_are_rejected() {
+        let result = UnicodeTable::from_data(groups(&["a", "b"]), &[None, Some(2)], Some(2), false);
+        assert!(matches!(result, Err(UnicodeTableError::GlyphIndexOutOfRange{index: 2, limit: 2})));
+        let result = UnicodeTable::from_data(groups(&["a"]), &[Some(u32::MAX)], None, false);
+        assert!(matches!(result, Err(UnicodeTableError::GlyphIndexOutOfRange{limit: MAX_PINNED_GLYPH_COUNT, ..})));
+        assert!(UnicodeTable::from_data(groups(&["a", "b"]), &[None, Some(1)], Some(2), false).is_ok());
+    }
+
+    #[test]
+    fn pins_to_the_same_index_collide() {
+        let result = UnicodeTable::from_data(groups(&["a", "b"]), &[Some(1), Some(1)], None, false);
+        assert!(matches!(result, Err(UnicodeTableError::GlyphIndexCollision{index: 1})));
+    }
+
+    #[test]
+    fn charset_with_byte_order_mark_parses() {
+        let table = read_table_file("bom", &[UTF8_BOM, b"U+0041\r\nU+0042, U+0062\r\n"].concat()).unwrap();
+        assert_eq!(table.data, [vec!["A".to_string()], vec!["B".to_string(), "b".to_string()]]);
+    }
+
+    #[test]
+    fn invalid_utf8_reports_its_offset() {
+        let result = read_table_file("invalid-utf8", &[UTF8_BOM, b"U+0041\n# caf\xe9\n"].concat());
+        assert!(matches!(result, Err(UnicodeTableError::InvalidUtf8{offset: 15})));
+    }
+
+    #[test]
+    fn unimap_export_round_trips() {
+        let data = vec![
+            vec!["\u{fffd}".to_string()],
+            vec!["A".to_string(), "\u{391}".to_string(), "\u{410}".to_string()],
+            vec!["\u{c5}".to_string(), "A\u{30a}".to_string()],
+            vec!["\u{1f600}".to_string()],
+        ];
+        let table = UnicodeTable{data: data.clone(), meta: UnicodeTableMeta::default(), pinned_len: 0};
+        let unimap = table.to_unimap();
+        assert!(unimap.lines().any(|line| line == "0x002\tU+00c5 U+0041,U+030a"));
+        assert_eq!(crate::unimap::parse_unimap(&unimap).unwrap(), data);
+
+        // the gaps before a pinned entry are kept, so the pinned glyph keeps its index.
+        let table = read_table_file("gaps", b"@0: U+0041\n@3: U+0042\n").unwrap();
+        let unimap = table.to_unimap();
+        assert!(unimap.lines().any(|line| line == "0x002"));
+        assert_eq!(crate::unimap::parse_unimap(&unimap).unwrap(), table.data);
+        assert_eq!(table.data[3], ["B"]);
+    }
+
+    #[test]
+    fn codepoints_beyond_unicode_are_invalid() {
+        assert_eq!(parse_codepoint("10FFFF").unwrap(), '\u{10ffff}');
+        for hex_digits in ["110000", "FFFFFFFFF", "D800"] {
+            assert!(matches!(parse_codepoint(hex_digits), 
+                Err(UnicodeTableError::InvalidCodepoint{codepoint}) if codepoint == hex_digits));
+        }
+        let result = read_table_file("beyond-unicode", b"U+0041\nU+110000\n");
+        assert!(matches!(result, Err(UnicodeTableError::InvalidCodepoint{codepoint}) if codepoint == "110000"));
+    }
+
+    #[test]
+    fn sort_by_codepoint_returns_order_for_glyphs() {
+        let mut table = UnicodeTable{data: vec![
+            vec!["c".to_string()], 
+            vec![], 
+            vec!["a\u{301}".to_string(), "\u{e1}".to_string()], 
+            vec!["b".to_string()], 
+            vec!["a".to_string()],
+        ], meta: Default::default(), pinned_len: 0};
+        // each glyph drawn from the first grapheme of its entry, as when rendering with the table.
+        let glyphs: Vec<String> = table.data.iter().map(|group| group.first().cloned().unwrap_or_default()).collect();
+        let order = table.sort_by_codepoint();
+        assert_eq!(order, [2, 4, 3, 0, 1]);
+        let sorted_glyphs: Vec<&String> = order.iter().map(|i| &glyphs[*i]).collect();
+        for (glyph, group) in sorted_glyphs.iter().zip(&table.data) {
+            assert_eq!(**glyph, group.first().cloned().unwrap_or_default());
+        }
+        assert!(table.data.last().unwrap().is_empty());
+    }
+}
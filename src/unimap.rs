@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use crate::errors::UnicodeTableError;
+
+use itertools::Itertools;
+use pest::iterators::Pair;
+use pest::Parser;
+use pest_derive::Parser;
+
+/// A parser for the console-tools `.uni` Unicode mapping format, as read by `psfaddtable` and
+/// `setfont -u`. Each line maps a glyph index (or a range of indices) to Unicode codepoints,
+/// codepoint ranges, comma-joined sequences, or `idem` (the codepoint equal to the index).
+#[derive(Parser)]
+#[grammar = "unimap_grammar.pest"]
+pub struct UnimapParser;
+
+/// Parses a `.uni` file into equivalence groups, one per glyph index. Every index from 0 up to
+/// the largest index mentioned must be present; a bare index line leaves its glyph unmapped.
+/// Surrogate codepoints inside codepoint ranges are skipped.
+pub fn parse_unimap(unparsed_file: &str) -> Result<Vec<Vec<String>>, UnicodeTableError> {
+    let file = UnimapParser::parse(Rule::file, unparsed_file)?
+        .next().unwrap(); // get and unwrap the 'file' rule; never fails
+
+    let mut glyphs: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for line in file.into_inner().filter(|l| l.as_rule() == Rule::line) {
+        let line_number = line.as_span().start_pos().line_col().0;
+        let mut parts = line.into_inner();
+        let mut indices = parts.next().unwrap().into_inner(); // every line starts with an index_range
+        let start = parse_index(indices.next().unwrap())?;
+        let end = match indices.next() {
+            Some(i) => parse_index(i)?,
+            None => start,
+        };
+        let mappings: Vec<Pair<Rule>> = parts.map(|m| m.into_inner().next().unwrap()).collect();
+
+        if start != end {
+            // a range of indices must be mapped by `idem` or by a codepoint range of the same length.
+            let first_codepoint = match mappings.as_slice() {
+                [m] if m.as_rule() == Rule::idem => start,
+                [m] if m.as_rule() == Rule::codepoint_range => {
+                    let mut range = m.clone().into_inner();
+                    let first = parse_codepoint(range.next().unwrap())?;
+                    let last = parse_codepoint(range.next().unwrap())?;
+                    if last < first || end < start || last - first != end - start {
+                        return Err(UnicodeTableError::InvalidUnimapRange{start, end, line: line_number});
+                    }
+                    first
+                }
+                _ => return Err(UnicodeTableError::InvalidUnimapRange{start, end, line: line_number}),
+            };
+            for (i, codepoint) in (start..=end).zip(first_codepoint..) {
+                // surrogate codepoints are not characters, so their glyphs are left unmapped.
+                glyphs.entry(i).or_default().extend(char::from_u32(codepoint).map(String::from));
+            }
+            continue;
+        }
+
+        let graphemes = glyphs.entry(start).or_default();
+        for mapping in mappings.into_iter() {
+            match mapping.as_rule() {
+                Rule::idem => graphemes.push(to_grapheme(&[start])?),
+                Rule::codepoint_range => {
+                    let mut range = mapping.into_inner();
+                    let first = parse_codepoint(range.next().unwrap())?;
+                    let last = parse_codepoint(range.next().unwrap())?;
+                    if last < first {
+                        return Err(UnicodeTableError::InvertedRange{start: first, end: last});
+                    }
+                    // surrogate codepoints inside the range are skipped, as in Unicode table files.
+                    graphemes.extend((first..=last).filter_map(char::from_u32).map(String::from));
+                }
+                _ => {
+                    let codepoints = mapping.into_inner().map(parse_codepoint).collect::<Result<Vec<u32>, _>>()?;
+                    graphemes.push(to_grapheme(&codepoints)?);
+                }
+            }
+        }
+    }
+
+    let mut data: Vec<Vec<String>> = vec![];
+    for (expected_index, (index, graphemes)) in (0..).zip(glyphs) {
+        if index != expected_index {
+            return Err(UnicodeTableError::MissingUnimapIndex{index: expected_index});
+        }
+        let mut graphemes: Vec<String> = graphemes.into_iter().unique().collect();
+        /* list single-character graphemes first */
+        graphemes.sort_by_key(|str| str.chars().count());
+        data.push(graphemes);
+    }
+    return Ok(data);
+}
+
+/// Parses a glyph index, which may be hexadecimal (`0x` prefix), octal (`0` prefix), or decimal.
+fn parse_index(index: Pair<Rule>) -> Result<u32, UnicodeTableError> {
+    let index = index.into_inner().next().unwrap();
+    let text = index.as_str();
+    return Ok(match index.as_rule() {
+        Rule::hex_index => u32::from_str_radix(&text[2..], 16)?,
+        Rule::octal_index => u32::from_str_radix(&text[1..], 8)?,
+        _ => text.parse::<u32>()?,
+    });
+}
+
+fn parse_codepoint(codepoint: Pair<Rule>) -> Result<u32, UnicodeTableError> {
+    let hex_digits = codepoint.into_inner().nth(1)
+        .expect("Unicode 'U+' prefix without codepoint found in unimap file");
+    return Ok(u32::from(crate::unicode_table::parse_codepoint(hex_digits.as_str())?));
+}
+
+fn to_grapheme(codepoints: &[u32]) -> Result<String, UnicodeTableError> {
+    return codepoints.iter()
+        .map(|c| char::from_u32(*c).ok_or(UnicodeTableError::InvalidCodepoint{codepoint: format!("{:04X}", c)}))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(groups: &[&[&str]]) -> Vec<Vec<String>> {
+        return groups.iter().map(|g| g.iter().map(|s| s.to_string()).collect()).collect();
+    }
+
+    /// The layout `psfgettable` writes: a comment header, then tab-separated hex indices.
+    const PSFGETTABLE_SAMPLE: &str = "\
+#
+# Character table extracted from font demo.psf
+#
+0x00\tU+fffd
+0x01\tU+263a
+0x02\tU+0041 U+0391 U+0410
+0x03\tU+00c5 U+212b U+0041,U+030a
+0x04-0x06\tU+2500-U+2502
+0x07\tidem
+";
+
+    #[test]
+    fn parses_psfgettable_export() {
+        assert_eq!(parse_unimap(PSFGETTABLE_SAMPLE).unwrap(), groups(&[
+            &["\u{fffd}"], 
+            &["\u{263a}"], 
+            &["A", "\u{391}", "\u{410}"], 
+            &["\u{c5}", "\u{212b}", "A\u{30a}"], 
+            &["\u{2500}"], &["\u{2501}"], &["\u{2502}"], 
+            &["\u{7}"],
+        ]));
+    }
+
+    #[test]
+    fn inverted_codepoint_range_is_an_error() {
+        assert!(matches!(parse_unimap("0x00 U+0042-U+0041\n"), 
+            Err(UnicodeTableError::InvertedRange{start: 0x42, end: 0x41})));
+        assert!(matches!(parse_unimap("0x00-0x01 U+0042-U+0041\n"), 
+            Err(UnicodeTableError::InvalidUnimapRange{start: 0, end: 1, line: 1})));
+    }
+
+    #[test]
+    fn surrogates_in_ranges_are_skipped() {
+        let data = parse_unimap("0x00 U+D7FF-U+E000\n").unwrap();
+        assert_eq!(data, groups(&[&["\u{d7ff}", "\u{e000}"]]));
+        // an index range keeps one glyph per index, leaving the surrogates' glyphs unmapped.
+        let data = parse_unimap("0x00-0x801 U+D7FF-U+E000\n").unwrap();
+        assert_eq!(data.len(), 0x802);
+        assert_eq!(data[0], ["\u{d7ff}"]);
+        assert!(data[1..0x801].iter().all(|g| g.is_empty()));
+        assert_eq!(data[0x801], ["\u{e000}"]);
+    }
+
+    #[test]
+    fn backslash_continues_a_line() {
+        let data = parse_unimap("0x00 U+0041 \\\n\tU+0391 # Latin and Greek\n0x01 U+0042\n").unwrap();
+        assert_eq!(data, groups(&[&["A", "\u{391}"], &["B"]]));
+    }
+
+    #[test]
+    fn errors_report_line_numbers() {
+        let text = "# header\n0x00 U+0041\n0x01-0x02 U+0042\n";
+        assert!(matches!(parse_unimap(text), Err(UnicodeTableError::InvalidUnimapRange{line: 3, ..})));
+        let Err(UnicodeTableError::UnimapParserError{error}) = parse_unimap("0x00 U+0041\n0x01 V+0042\n") else {
+            panic!("expected a parser error");
+        };
+        assert!(matches!(error.line_col, pest::error::LineColLocation::Pos((2, _))));
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped() {
+        let path = std::env::temp_dir().join(format!("otf2psf-unimap-bom-{}.uni", std::process::id()));
+        std::fs::write(&path, [&[0xef, 0xbb, 0xbf], PSFGETTABLE_SAMPLE.as_bytes()].concat()).unwrap();
+        let table = crate::unicode_table::UnicodeTable::from_unimap_file(&path, None, false);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(table.unwrap().data, parse_unimap(PSFGETTABLE_SAMPLE).unwrap());
+    }
+}
@@ -0,0 +1,40 @@
+//! A fallback font whose cells differ in size from the input font's must be rejected, unless
+//! `convert` is told to pad glyphs to a common size.
+
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+const DEMO_FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/demo.ttf");
+
+/// Converts the demo font at 8 px with a copy of it whose `.notdef`, and so space cell, is wider
+/// as a fallback font, adding the arguments `args`.
+fn convert_with_wide_fallback(name: &str, args: &[&str]) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fallback_cells").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut font_data = std::fs::read(DEMO_FONT).unwrap();
+    // `hmtx` starts at byte 248, with the advance of `.notdef` first.
+    font_data[248..250].copy_from_slice(&1000u16.to_be_bytes());
+    std::fs::write(dir.join("wide.ttf"), &font_data).unwrap();
+    std::fs::write(dir.join("table.set"), "U+0041\n").unwrap();
+    return Command::new(env!("CARGO_BIN_EXE_otf2psf"))
+        .arg("convert").arg(DEMO_FONT).arg(dir.join("font.psf")).arg("8")
+        .arg("-u").arg(dir.join("table.set")).arg("--fallback-font").arg(dir.join("wide.ttf")).args(args)
+        .output().unwrap();
+}
+
+#[test]
+fn wider_fallback_is_rejected() {
+    let output = convert_with_wide_fallback("rejected", &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("FallbackCellSize"));
+}
+
+#[test]
+fn wider_fallback_is_accepted_with_padding() {
+    for pad in ["--pad", "--pad-to-mode-size"] {
+        let output = convert_with_wide_fallback(pad, &[pad]);
+        assert!(output.status.success(), "{} failed: {}", pad, String::from_utf8_lossy(&output.stderr));
+    }
+}
@@ -0,0 +1,34 @@
+//! The point size a PFF2 font declares must be the requested height, or the PPEM given with
+//! `--ppem-override`.
+
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const DEMO_FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/demo.ttf");
+
+/// Converts the demo font to PFF2 at 8 px with the extra arguments `args`, and returns the value
+/// of the `PTSZ` section.
+fn point_size(name: &str, args: &[&str]) -> u16 {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("pff2_point_size").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("font.pf2");
+    let status = Command::new(env!("CARGO_BIN_EXE_otf2psf"))
+        .arg("convert").arg(DEMO_FONT).arg(&output).args(["8", "-g", "128", "--pad", "--format", "pff2"]).args(args)
+        .status().unwrap();
+    assert!(status.success(), "convert with {:?} failed", args);
+    let font = std::fs::read(&output).unwrap();
+    let start = font.windows(4).position(|w| w == b"PTSZ").unwrap() + 8;
+    return u16::from_be_bytes([font[start], font[start + 1]]);
+}
+
+#[test]
+fn point_size_defaults_to_the_height() {
+    assert_eq!(point_size("default", &[]), 8);
+}
+
+#[test]
+fn point_size_follows_ppem_override() {
+    assert_eq!(point_size("override", &["--ppem-override", "20"]), 20);
+}
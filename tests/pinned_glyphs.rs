@@ -0,0 +1,37 @@
+//! Glyphs pinned to an index in a Unicode table file must keep that index through the later
+//! convert passes, and in the exported unimap.
+
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const DEMO_FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/demo.ttf");
+
+/// Converts the demo font with the Unicode table file `table`, merging identical glyphs, and
+/// returns the lines of the `--write-unimap` export, without its comment header.
+fn dedup_unimap(name: &str, table: &str) -> Vec<String> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("pinned_glyphs").join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("table.set"), table).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_otf2psf"))
+        .arg("convert").arg(DEMO_FONT).arg(dir.join("font.psf")).args(["8", "--pad", "--allow-missing", "--dedup"])
+        .arg("-u").arg(dir.join("table.set")).arg("--write-unimap").arg(dir.join("table.uni"))
+        .status().unwrap();
+    assert!(status.success(), "convert with {:?} failed", table);
+    let unimap = std::fs::read_to_string(dir.join("table.uni")).unwrap();
+    return unimap.lines().filter(|line| !line.starts_with('#')).map(String::from).collect();
+}
+
+#[test]
+fn dedup_keeps_blank_gaps_before_a_pinned_glyph() {
+    // 'A' fills index 0, leaving two identical blank glyphs before the pinned 'B'.
+    assert_eq!(dedup_unimap("gaps", "U+0041\n@3: U+0042\n"), ["0x000\tU+0041", "0x001", "0x002", "0x003\tU+0042"]);
+}
+
+#[test]
+fn dedup_merges_later_glyphs_into_a_pinned_one() {
+    // 'B', 'C', and 'D' all draw the demo font's `.notdef` box; 'B' is pinned and stays put.
+    assert_eq!(dedup_unimap("tail", "U+0041\n@1: U+0042\nU+0043\nU+0044\n"), 
+        ["0x000\tU+0041", "0x001\tU+0042 U+0043 U+0044"]);
+}
@@ -0,0 +1,66 @@
+//! Converting the same font twice with the same options must give byte-identical files in every
+//! output format, so that packagers can checksum generated fonts.
+
+#![allow(clippy::needless_return)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const DEMO_FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/demo.ttf");
+
+/// Runs `otf2psf convert --repeatable` on the demo font, writing into `dir`, and returns the bytes
+/// of the output file followed by those of the `--svg` and `--write-unimap` exports, if written.
+fn convert(dir: &Path, output_name: &str, args: &[&str]) -> Vec<Option<Vec<u8>>> {
+    std::fs::create_dir_all(dir).unwrap();
+    let output = dir.join(output_name);
+    let svg = dir.join("sheet.svg");
+    let unimap = dir.join("table.uni");
+    let status = Command::new(env!("CARGO_BIN_EXE_otf2psf"))
+        .arg("convert").arg(DEMO_FONT).arg(&output).args(args).arg("--repeatable")
+        .arg("--svg").arg(&svg).arg("--write-unimap").arg(&unimap)
+        .status().unwrap();
+    assert!(status.success(), "convert {:?} failed", args);
+    return [output, svg, unimap].iter().map(|p| std::fs::read(p).ok()).collect();
+}
+
+fn assert_repeatable(output_name: &str, args: &[&str]) {
+    let tmp = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("reproducible").join(output_name);
+    let first = convert(&tmp.join("first"), output_name, args);
+    let second = convert(&tmp.join("second"), output_name, args);
+    assert!(first == second, "converting to {} twice gave different bytes", output_name);
+}
+
+#[test]
+fn psf2_is_repeatable() {
+    assert_repeatable("font.psf", &["8", "-g", "128", "--pad"]);
+}
+
+#[test]
+fn gzipped_psf2_is_repeatable() {
+    assert_repeatable("font.psf.gz", &["8", "-g", "128", "--pad"]);
+}
+
+#[test]
+fn psf1_is_repeatable() {
+    assert_repeatable("font.psf1", &["10", "-g", "128", "--pad", "--cell-width", "em", "--format", "psf1"]);
+}
+
+#[test]
+fn pff2_is_repeatable() {
+    assert_repeatable("font.pf2", &["8", "-g", "128", "--pad", "--format", "pff2"]);
+}
+
+#[test]
+fn c_header_is_repeatable() {
+    assert_repeatable("font.h", &["8", "-g", "128", "--pad", "--format", "c-header"]);
+}
+
+#[test]
+fn png_sheet_is_repeatable() {
+    assert_repeatable("font.png", &["8", "-g", "128", "--pad", "--format", "png-sheet"]);
+}
+
+#[test]
+fn proportional_bin_is_repeatable() {
+    assert_repeatable("font.bin", &["8", "-g", "128", "--format", "proportional-bin"]);
+}
@@ -0,0 +1,35 @@
+//! Fonts that report zero advance width for every glyph must still convert to visible glyphs,
+//! drawn on em-wide canvases instead.
+
+#![allow(clippy::needless_return)]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+const DEMO_FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/demo.ttf");
+/// The size of a PSF2 header, after which the glyph bitmaps start.
+const PSF2_HEADER_SIZE: usize = 32;
+
+#[test]
+fn zero_advance_font_falls_back_to_em_width() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("zero_advance");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut font_data = std::fs::read(DEMO_FONT).unwrap();
+    // zero the advances of `.notdef` and 'A' in `hmtx`, which starts at byte 248.
+    font_data[248..250].fill(0);
+    font_data[252..254].fill(0);
+    std::fs::write(dir.join("zero.ttf"), &font_data).unwrap();
+    std::fs::write(dir.join("table.set"), "U+0041\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_otf2psf"))
+        .arg("convert").arg(dir.join("zero.ttf")).arg(dir.join("font.psf")).args(["16", "--pad"])
+        .arg("-u").arg(dir.join("table.set"))
+        .output().unwrap();
+    assert!(output.status.success(), "convert failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("zero advance width"));
+
+    let font = std::fs::read(dir.join("font.psf")).unwrap();
+    let width = u32::from_le_bytes(font[28..32].try_into().unwrap());
+    assert!(width > 0);
+    assert!(font[PSF2_HEADER_SIZE..].iter().any(|b| *b != 0), "'A' was rendered blank");
+}